@@ -0,0 +1,48 @@
+//! Session-wide aborts.
+//!
+//! A role that fails sends `abort <origin> <reason>` on every link it still
+//! holds before it hangs up. A role that reads that line from a peer's
+//! [`Channel`](crate::channel::Channel) fails at once with
+//! [`ProtocolError::PeerAborted`] and passes the same origin and reason on to
+//! its own peers, so the whole session stops together and every party reports
+//! the failure that started it rather than a timeout of its own. Lines read
+//! from the terminal or a file are never taken for aborts.
+
+use tokio::io::AsyncWrite;
+use tokio::time::{timeout, Duration};
+use tracing::debug;
+
+use crate::error::{self, ProtocolError};
+use crate::wire;
+
+const TAG: &str = "abort";
+
+/// How long to try telling a peer about an abort; it may already be gone.
+const NOTIFY_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Fail with the abort `line` carries, if it is one.
+pub fn check(line: &str) -> anyhow::Result<()> {
+    let Some(rest) = line.strip_prefix(TAG).and_then(|r| r.strip_prefix(' ')) else {
+        return Ok(());
+    };
+    let (origin, reason) = rest.split_once(' ').unwrap_or((rest, ""));
+    Err(ProtocolError::PeerAborted { origin: origin.to_string(), reason: reason.to_string() }.into())
+}
+
+/// The abort line announcing that `me` failed with `error`, or passing on
+/// the original abort if `error` is one.
+pub fn line(me: &str, error: &anyhow::Error) -> String {
+    match error::protocol_error(error) {
+        Some(ProtocolError::PeerAborted { origin, reason }) => format!("{} {} {}", TAG, origin, reason),
+        _ => format!("{} {} {}", TAG, me, format!("{:#}", error).replace('\n', " ")),
+    }
+}
+
+/// Tell `peer` that the session is aborted because of `error`. Failures are
+/// ignored: the session is over either way.
+pub async fn notify<W: AsyncWrite + Unpin>(writer: &mut W, peer: &str, me: &str, error: &anyhow::Error) {
+    let sent = timeout(NOTIFY_TIMEOUT, wire::write_line(writer, peer, &line(me, error))).await;
+    if matches!(sent, Ok(Ok(()))) {
+        debug!("Sent abort to {}", peer);
+    }
+}
@@ -0,0 +1,18 @@
+//! Additive `n`-out-of-`n` sharing: a secret is split into `n` random field
+//! elements that sum to it, so a data owner can hand one to each compute party.
+
+use crate::field::Field;
+use crate::randomness::RandomSource;
+
+/// Split `secret` into `parties` uniformly random shares summing to it mod p.
+pub fn split<R: RandomSource + ?Sized>(field: &Field, rng: &mut R, secret: u64, parties: usize) -> Vec<u64> {
+    let mut shares = rng.field_elements(field, parties - 1);
+    let rest = field.sub(field.reduce(secret), field.sum(shares.iter().copied()));
+    shares.push(rest);
+    shares
+}
+
+/// Recombine additive shares into the value they hide.
+pub fn combine(field: &Field, shares: &[u64]) -> u64 {
+    field.sum(shares.iter().copied())
+}
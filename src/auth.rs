@@ -0,0 +1,200 @@
+//! Party identities and the authenticated handshake run on every link.
+//!
+//! Each role (`server`, `client1`, `client2`, ..., `owner`) has an Ed25519
+//! keypair. With `--keys DIR`, a role loads its secret key from `DIR/<name>.key`
+//! and the public keys of the peers it talks to from `DIR/<name>.pub`; only the
+//! `.pub` files need to be shared between machines. `keygen` creates them.
+//! Party `k` of the `party` binary uses the `client<k>` identity, matching the
+//! server socket it connects to.
+//!
+//! Right after a link opens, both ends send `hello <name> <ephemeral key>`
+//! with a fresh X25519 public key, then `auth <signature>` over both names and
+//! both ephemeral keys. A process that does not hold the expected peer's secret
+//! key cannot produce the signature, and a signature from an earlier connection
+//! does not verify against fresh ephemeral keys. The Diffie-Hellman secret of
+//! the two ephemeral keys then keys the link's encryption (see `encryption`).
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use hkdf::Hkdf;
+use sha2::Sha256;
+use tracing::info;
+use x25519_dalek::{PublicKey, StaticSecret};
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::cli;
+use crate::encryption;
+use crate::error::ProtocolError;
+use crate::randomness::RandomSource;
+use crate::transport::Link;
+use crate::wire;
+
+const CONTEXT: &str = "du-atallah auth v2";
+
+/// A role's name and signing key.
+pub struct Identity {
+    name: String,
+    signing: SigningKey,
+}
+
+impl Identity {
+    pub fn generate(name: &str, rng: &mut dyn RandomSource) -> Self {
+        let mut seed = Zeroizing::new([0u8; 32]);
+        rng.fill_bytes(seed.as_mut());
+        Identity { name: name.to_string(), signing: SigningKey::from_bytes(&seed) }
+    }
+
+    /// Load `dir/<name>.key`.
+    pub fn load(dir: &Path, name: &str) -> anyhow::Result<Self> {
+        let path = dir.join(format!("{}.key", name));
+        let seed: Zeroizing<[u8; 32]> = Zeroizing::new(read_hex(&path)?);
+        Ok(Identity { name: name.to_string(), signing: SigningKey::from_bytes(&seed) })
+    }
+
+    /// Write `dir/<name>.key` (owner-readable only) and `dir/<name>.pub`.
+    pub fn save(&self, dir: &Path) -> anyhow::Result<()> {
+        let key_path = dir.join(format!("{}.key", self.name));
+        fs::write(&key_path, Zeroizing::new(hex::encode(self.signing.to_bytes()) + "\n").as_bytes())?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&key_path, fs::Permissions::from_mode(0o600))?;
+        }
+        fs::write(dir.join(format!("{}.pub", self.name)), hex::encode(self.public_key().to_bytes()) + "\n")?;
+        Ok(())
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn public_key(&self) -> VerifyingKey {
+        self.signing.verifying_key()
+    }
+
+    pub fn sign(&self, message: &[u8]) -> Signature {
+        self.signing.sign(message)
+    }
+}
+
+/// Our identity plus the directory holding the peers' public keys.
+pub struct Keyring {
+    pub identity: Identity,
+    dir: PathBuf,
+}
+
+impl Keyring {
+    pub fn load(dir: impl Into<PathBuf>, name: &str) -> anyhow::Result<Self> {
+        let dir = dir.into();
+        Ok(Keyring { identity: Identity::load(&dir, name)?, dir })
+    }
+
+    /// The keyring named by `--keys`, or `None` when peers are not authenticated.
+    pub fn from_args(name: &str) -> anyhow::Result<Option<Self>> {
+        let keyring = cli::flag("--keys").map(|dir| Self::load(dir, name)).transpose()?;
+        match &keyring {
+            Some(k) => info!("Authentication: Ed25519 as {} ({}), links encrypted", name, k.dir.display()),
+            None => info!("Authentication: off (pass --keys DIR to authenticate and encrypt links)"),
+        }
+        Ok(keyring)
+    }
+
+    /// Public key of `peer`, read from `<dir>/<peer>.pub`.
+    pub fn peer_key(&self, peer: &str) -> anyhow::Result<VerifyingKey> {
+        let bytes: [u8; 32] = read_hex(&self.dir.join(format!("{}.pub", peer)))?;
+        VerifyingKey::from_bytes(&bytes).map_err(|e| ProtocolError::AuthFailed(format!("Invalid public key for {}: {}", peer, e)).into())
+    }
+
+    /// Authenticate `peer` on `link` against its public key from the key directory.
+    pub async fn authenticate(&self, link: &mut Link, peer: &str, rng: &mut dyn RandomSource) -> anyhow::Result<()> {
+        handshake(link, &self.identity, peer, &self.peer_key(peer)?, rng).await
+    }
+}
+
+/// Run [`Keyring::authenticate`] when `keyring` is set.
+pub async fn authenticate(keyring: Option<&Keyring>, link: &mut Link, peer: &str, rng: &mut dyn RandomSource) -> anyhow::Result<()> {
+    match keyring {
+        Some(k) => k.authenticate(link, peer, rng).await,
+        None => Ok(()),
+    }
+}
+
+/// Prove to `peer` that we are `me`, check that the other end holds
+/// `peer_key`, and switch `link` to encrypted traffic keyed by the handshake.
+pub async fn handshake(
+    link: &mut Link,
+    me: &Identity,
+    peer: &str,
+    peer_key: &VerifyingKey,
+    rng: &mut dyn RandomSource,
+) -> anyhow::Result<()> {
+    let mut seed = [0u8; 32];
+    rng.fill_bytes(&mut seed);
+    let ephemeral = StaticSecret::from(seed);
+    seed.zeroize();
+    let ours = hex::encode(PublicKey::from(&ephemeral).as_bytes());
+    wire::write_line(&mut link.writer, peer, &format!("hello {} {}", me.name, ours)).await?;
+
+    let hello = wire::read_line(&mut link.reader, peer).await?;
+    let (claimed, theirs) = match hello.split(' ').collect::<Vec<_>>()[..] {
+        ["hello", name, key] => (name.to_string(), key.to_string()),
+        _ => return Err(ProtocolError::AuthFailed(format!("Authentication of {} failed: malformed hello", peer)).into()),
+    };
+    if claimed != peer {
+        return Err(ProtocolError::AuthFailed(format!("Authentication failed: expected {}, peer claims to be {}", peer, claimed)).into());
+    }
+    let peer_ephemeral: [u8; 32] = hex::decode(&theirs)
+        .ok()
+        .and_then(|b| b.try_into().ok())
+        .ok_or_else(|| ProtocolError::AuthFailed(format!("Authentication of {} failed: malformed ephemeral key", peer)))?;
+
+    let signature = me.signing.sign(transcript(&me.name, peer, &ours, &theirs).as_bytes());
+    wire::write_line(&mut link.writer, peer, &format!("auth {}", hex::encode(signature.to_bytes()))).await?;
+
+    let auth = wire::read_line(&mut link.reader, peer).await?;
+    let signature = auth
+        .strip_prefix("auth ")
+        .and_then(|s| hex::decode(s).ok())
+        .and_then(|b| Signature::from_slice(&b).ok())
+        .ok_or_else(|| ProtocolError::AuthFailed(format!("Authentication of {} failed: malformed signature", peer)))?;
+    peer_key
+        .verify(transcript(peer, &me.name, &theirs, &ours).as_bytes(), &signature)
+        .map_err(|_| ProtocolError::AuthFailed(format!("Authentication of {} failed: bad signature", peer)))?;
+
+    let shared = ephemeral.diffie_hellman(&PublicKey::from(peer_ephemeral));
+    if !shared.was_contributory() {
+        return Err(ProtocolError::AuthFailed(format!("Authentication of {} failed: degenerate ephemeral key", peer)).into());
+    }
+    let hkdf = Hkdf::<Sha256>::new(None, shared.as_bytes());
+    let send_key = Zeroizing::new(direction_key(&hkdf, &transcript(&me.name, peer, &ours, &theirs)));
+    let recv_key = Zeroizing::new(direction_key(&hkdf, &transcript(peer, &me.name, &theirs, &ours)));
+
+    let plain = std::mem::replace(link, Link { reader: Box::new(tokio::io::empty()), writer: Box::new(tokio::io::sink()), session: None });
+    *link = encryption::wrap(plain, peer, &send_key, &recv_key);
+
+    info!("{}: Authenticated {}, link encrypted", me.name, peer);
+    Ok(())
+}
+
+/// Key for traffic in the direction `info` names, sender first.
+fn direction_key(hkdf: &Hkdf<Sha256>, info: &str) -> [u8; 32] {
+    let mut key = [0u8; 32];
+    hkdf.expand(info.as_bytes(), &mut key).expect("32 bytes is a valid HKDF-SHA256 output length");
+    key
+}
+
+/// Both names and ephemeral keys, `from`'s first. `from` signs it, and it
+/// names the direction `from -> to` when deriving traffic keys.
+fn transcript(from: &str, to: &str, from_key: &str, to_key: &str) -> String {
+    format!("{}\n{}\n{}\n{}\n{}", CONTEXT, from, to, from_key, to_key)
+}
+
+fn read_hex<const N: usize>(path: &Path) -> anyhow::Result<[u8; N]> {
+    let text = fs::read_to_string(path).map_err(|e| ProtocolError::InvalidInput(format!("Cannot read {}: {}", path.display(), e)))?;
+    hex::decode(text.trim())
+        .ok()
+        .and_then(|b| b.try_into().ok())
+        .ok_or_else(|| ProtocolError::InvalidInput(format!("{} does not hold a {}-byte hex key", path.display(), N)).into())
+}
@@ -0,0 +1,45 @@
+use std::fs;
+use std::path::Path;
+use std::process::ExitCode;
+use du_attalah::auth::Identity;
+use du_attalah::cli;
+use du_attalah::error::{self, ProtocolError};
+use du_attalah::randomness::OsRandom;
+use du_attalah::report::{Output, Report};
+
+/// Create Ed25519 identities: `keygen --keys DIR <name>...`, e.g.
+/// `keygen --keys keys server client1 client2 owner`. Each name gets
+/// `DIR/<name>.key`, to keep on that role's machine, and `DIR/<name>.pub`,
+/// to hand to every peer.
+fn main() -> ExitCode {
+    let output = match Output::from_args() {
+        Ok(output) => output,
+        Err(e) => return error::exit(Err(e)),
+    };
+    let mut report = Report::new("keygen");
+    let result = run(output, &mut report);
+    error::exit(output.finish(report, result))
+}
+
+fn run(output: Output, report: &mut Report) -> anyhow::Result<()> {
+    let dir = cli::flag("--keys").ok_or_else(|| ProtocolError::InvalidInput("Missing --keys DIR".into()))?;
+    let names = cli::positional();
+    if names.is_empty() {
+        return Err(ProtocolError::InvalidInput("Usage: keygen --keys DIR <name>...".into()).into());
+    }
+
+    fs::create_dir_all(&dir)?;
+    let mut rng = OsRandom::new();
+    let mut files = Vec::new();
+    for name in &names {
+        let identity = Identity::generate(name, &mut rng);
+        identity.save(Path::new(&dir))?;
+        if output == Output::Text {
+            println!("Keygen: {} -> {}/{}.key, {}/{}.pub", name, dir, name, dir, name);
+        }
+        files.extend([format!("{}/{}.key", dir, name), format!("{}/{}.pub", dir, name)]);
+    }
+    report.files = Some(files);
+
+    Ok(())
+}
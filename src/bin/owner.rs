@@ -0,0 +1,124 @@
+use std::process::ExitCode;
+use tokio::net::UnixStream;
+use tokio::time::timeout;
+use du_attalah::additive;
+use du_attalah::auth::{self, Keyring};
+use du_attalah::cli;
+use du_attalah::error::{self, Phase, ProtocolError};
+use du_attalah::field::{Field, PRIME_MOD};
+use du_attalah::input;
+use du_attalah::logging;
+use du_attalah::randomness;
+use du_attalah::report::{Output, Report};
+use du_attalah::share_file::{self, ShareFile};
+use du_attalah::transport::Link;
+use du_attalah::wire::{self, READ_TIMEOUT};
+use tracing::info;
+
+/// Tools for a data owner who outsources values to the compute clients.
+///
+/// `owner share [--parties N] [--modulus M] <values>...` splits every value
+/// into `N` additive shares and sends client `k` its share of each. Every
+/// `<values>` argument becomes one input line on each client, so
+/// `owner share 12 7` feeds the `mul` prompts for x and y and
+/// `owner share "1 2 3 4"` feeds a `stats` dataset. Start the clients with
+/// `--from-owner` first.
+///
+/// `owner reconstruct <share files>...` reads the share files parties wrote
+/// with `--share-file`, checks they come from the same session and field, and
+/// prints the opened value.
+#[tokio::main]
+async fn main() -> ExitCode {
+    logging::init();
+    let output = match Output::from_args() {
+        Ok(output) => output,
+        Err(e) => return error::exit(Err(e)),
+    };
+    let mut report = Report::new("owner");
+    let mut args = cli::positional().into_iter();
+    let result = match args.next().as_deref() {
+        Some("share") => share(args.collect()).await,
+        Some("reconstruct") => reconstruct(args.collect(), output, &mut report),
+        Some(other) => Err(ProtocolError::InvalidInput(format!("Unknown subcommand: {}", other)).into()),
+        None => Err(ProtocolError::InvalidInput("Usage: owner share [--parties N] [--modulus M] <values>... | owner reconstruct <share files>...".into()).into()),
+    };
+    error::exit(output.finish(report, result))
+}
+
+async fn share(args: Vec<String>) -> anyhow::Result<()> {
+    let parties: usize = cli::parse_flag("--parties", 2)?;
+    let field = Field::try_new(cli::parse_flag("--modulus", PRIME_MOD)?)?;
+    if parties < 2 {
+        return Err(ProtocolError::InvalidInput(format!("At least two parties are required, got {}", parties)).into());
+    }
+
+    let mut lines = Vec::new();
+    for arg in args {
+        let values = arg
+            .split_whitespace()
+            .map(|v| v.parse::<u64>().map_err(|e| ProtocolError::InvalidInput(format!("Invalid value {}: {}", v, e))))
+            .collect::<Result<Vec<u64>, _>>()?;
+        if values.is_empty() {
+            return Err(ProtocolError::InvalidInput("Empty value list".into()).into());
+        }
+        lines.push(values);
+    }
+    if lines.is_empty() {
+        return Err(ProtocolError::InvalidInput("No values to share".into()).into());
+    }
+
+    // per_party[k][line][i] is party k+1's share of value i on that line
+    let mut rng = randomness::from_args(randomness::OWNER_STREAM)?;
+    info!("Owner: Drawing shares from {}", rng.name());
+    let mut per_party = vec![vec![Vec::new(); lines.len()]; parties];
+    for (l, values) in lines.iter().enumerate() {
+        for &value in values {
+            for (k, s) in additive::split(&field, rng.as_mut(), value, parties).into_iter().enumerate() {
+                per_party[k][l].push(s);
+            }
+        }
+    }
+
+    let keyring = Keyring::from_args("owner")?;
+    for (k, party_lines) in (1..=parties).zip(per_party) {
+        let socket = input::owner_socket(k);
+        let peer = format!("Client{}", k);
+        let stream = match timeout(READ_TIMEOUT, UnixStream::connect(&socket)).await {
+            Ok(Ok(stream)) => stream,
+            Ok(Err(e)) => {
+                return Err(anyhow::Error::new(ProtocolError::disconnected(&peer))
+                    .context(format!("Cannot reach {} at {}: {}", peer, socket, e)));
+            },
+            Err(_) => return Err(ProtocolError::timeout(Phase::Connect, peer).into())
+        };
+        let mut link = Link::from_unix(stream);
+        auth::authenticate(keyring.as_ref(), &mut link, &format!("client{}", k), rng.as_mut()).await?;
+        for values in &party_lines {
+            wire::write_values(&mut link.writer, &peer, values).await?;
+        }
+        info!("Owner: Sent {} share line(s) to {}", party_lines.len(), peer);
+    }
+
+    Ok(())
+}
+
+fn reconstruct(paths: Vec<String>, output: Output, report: &mut Report) -> anyhow::Result<()> {
+    if paths.is_empty() {
+        return Err(ProtocolError::InvalidInput("No share files given".into()).into());
+    }
+    let files = paths.iter().map(ShareFile::read).collect::<anyhow::Result<Vec<_>>>()?;
+    for (path, file) in paths.iter().zip(&files) {
+        info!("Owner: {} holds party {}'s share of session {}", path, file.party, file.session);
+    }
+
+    let opened = share_file::reconstruct(&files)?;
+    let first = &files[0];
+    report.joined(first.session, first.operation);
+    report.reconstructed(&opened);
+    if output == Output::Text {
+        let opened: Vec<String> = opened.iter().map(u64::to_string).collect();
+        println!("Session {} ({} mod {}): {}", first.session, first.operation, first.modulus, opened.join(" "));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,127 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::process::ExitCode;
+use std::sync::Arc;
+use tokio::net::{UnixListener, UnixStream};
+use tokio::time::timeout;
+use du_attalah::auth::{self, Keyring};
+use du_attalah::channel::Channel;
+use du_attalah::cli;
+use du_attalah::error::{self, Phase, ProtocolError};
+use du_attalah::input;
+use du_attalah::logging;
+use du_attalah::randomness::{self, RandomSource};
+use du_attalah::report::{Output, Report};
+use du_attalah::resume;
+use du_attalah::roles::party;
+use du_attalah::shamir;
+use du_attalah::share_file::Scheme;
+use du_attalah::transport::Link;
+use du_attalah::wire::READ_TIMEOUT;
+use tracing::info;
+
+/// One data owner in an n-party multiplication. Run one instance per party:
+/// `party --index K --parties N [--scheme additive|shamir] [--threshold T]`,
+/// with the server started as `server --parties N`.
+#[tokio::main]
+async fn main() -> ExitCode {
+    logging::init();
+    let output = match Output::from_args() {
+        Ok(output) => output,
+        Err(e) => return error::exit(Err(e)),
+    };
+    let mut report = Report::new("party");
+    let result = join(&mut report).await;
+    error::exit(output.finish(report, result))
+}
+
+async fn join(report: &mut Report) -> anyhow::Result<()> {
+    let parties: usize = cli::parse_flag("--parties", 2)?;
+    let me: usize = cli::flag("--index")
+        .ok_or_else(|| ProtocolError::InvalidInput("Missing --index".into()))?
+        .parse()
+        .map_err(|e| ProtocolError::InvalidInput(format!("Invalid value for --index: {}", e)))?;
+    report.role = format!("party{}", me);
+    if parties < 2 || me == 0 || me > parties {
+        return Err(ProtocolError::InvalidInput(format!("Party index {} is not in 1..={}", me, parties)).into());
+    }
+    let threshold: usize = cli::parse_flag("--threshold", parties.div_ceil(2))?;
+    let scheme = match cli::flag("--scheme").as_deref() {
+        None | Some("additive") => Scheme::Additive,
+        Some("shamir") => {
+            shamir::check_params(threshold, parties)?;
+            Scheme::Shamir { threshold }
+        },
+        Some(other) => return Err(ProtocolError::InvalidInput(format!("Unknown sharing scheme: {}", other)).into()),
+    };
+    let keyring = Keyring::from_args(&format!("client{}", me))?.map(Arc::new);
+    let grace = resume::grace_from_args()?;
+    let mut rng = randomness::from_args(me as u64)?;
+
+    // Higher-numbered parties connect to this socket for their pairwise exchange
+    let p2p_socket = format!("/tmp/p2p_party{}", me);
+    let _ = fs::remove_file(&p2p_socket);
+    let p2p_listener = UnixListener::bind(&p2p_socket)?;
+
+    // Connect to server
+    let server_socket = format!("/tmp/da_client{}", me);
+    info!("Party{}: Connecting to server at {}", me, server_socket);
+    let server_stream = match timeout(READ_TIMEOUT, UnixStream::connect(&server_socket)).await {
+        Ok(Ok(stream)) => stream,
+        Ok(Err(e)) => return Err(e.into()),
+        Err(_) => return Err(ProtocolError::timeout(Phase::Connect, "server").into())
+    };
+    let mut server = Link::from_unix(server_stream);
+    auth::authenticate(keyring.as_deref(), &mut server, "server", rng.as_mut()).await?;
+    // Only the server link is resumable; the links between parties are not
+    let mut server = resume::resumable(server, "server", resume::dial(&server_socket, "server", keyring.clone(), grace));
+    report.connected();
+
+    let mut input = input::open(me, keyring.as_deref(), rng.as_mut()).await?;
+
+    let peers = async |rng: &mut dyn RandomSource| link_peers(me, parties, &p2p_listener, keyring.as_deref(), rng).await;
+    let share_file = party::run(me, parties, scheme, &mut server, &mut input, rng.as_mut(), report, peers).await?;
+    share_file.save_if_requested()?;
+    report.share = Some(share_file.values.clone());
+
+    Ok(())
+}
+
+/// Connect to every lower-numbered peer and accept every higher-numbered one,
+/// authenticating each as `client<peer>` when `keyring` is set.
+async fn link_peers(
+    me: usize,
+    parties: usize,
+    p2p_listener: &UnixListener,
+    keyring: Option<&Keyring>,
+    rng: &mut dyn RandomSource,
+) -> anyhow::Result<BTreeMap<usize, Link>> {
+    let mut links = BTreeMap::new();
+    for peer in 1..me {
+        let socket = format!("/tmp/p2p_party{}", peer);
+        let stream = match timeout(READ_TIMEOUT, UnixStream::connect(&socket)).await {
+            Ok(Ok(stream)) => stream,
+            Ok(Err(e)) => return Err(e.into()),
+            Err(_) => return Err(ProtocolError::timeout(Phase::Connect, format!("Party{}", peer)).into())
+        };
+        let mut link = Link::from_unix(stream);
+        Channel::new(&mut link, format!("Party{}", peer)).send(&me).await?;
+        auth::authenticate(keyring, &mut link, &format!("client{}", peer), rng).await?;
+        links.insert(peer, link);
+    }
+    for _ in me + 1..=parties {
+        let (stream, _) = match timeout(READ_TIMEOUT, p2p_listener.accept()).await {
+            Ok(Ok(stream)) => stream,
+            Ok(Err(e)) => return Err(e.into()),
+            Err(_) => return Err(ProtocolError::timeout(Phase::Accept, "peer").into())
+        };
+        let mut link = Link::from_unix(stream);
+        let peer: usize = Channel::new(&mut link, "peer").recv().await?;
+        if peer <= me || peer > parties || links.contains_key(&peer) {
+            return Err(ProtocolError::Malformed(format!("Unexpected peer connection claiming to be Party{}", peer)).into());
+        }
+        auth::authenticate(keyring, &mut link, &format!("client{}", peer), rng).await?;
+        links.insert(peer, link);
+    }
+    Ok(links)
+}
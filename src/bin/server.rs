@@ -1,230 +1,83 @@
 use tokio::net::UnixListener;
-use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
-use rand::Rng;
 use std::fs;
-use tokio::time::{timeout, Duration};
-
-const PRIME_MOD: i64 = 1e9 as i64 + 7; 
-const READ_TIMEOUT: Duration = Duration::from_secs(30);
+use std::process::ExitCode;
+use std::sync::Arc;
+use tokio::time::timeout;
+use du_attalah::auth::{self, Keyring};
+use du_attalah::cli;
+use du_attalah::error::{self, Phase, ProtocolError};
+use du_attalah::logging;
+use du_attalah::randomness;
+use du_attalah::report::{Output, Report};
+use du_attalah::rendezvous::{self, Topology};
+use du_attalah::resume;
+use du_attalah::roles::server;
+use du_attalah::transport::Link;
+use du_attalah::wire::READ_TIMEOUT;
+use tracing::info;
 
 #[tokio::main]
-async fn main() -> anyhow::Result<()> {
-    let socket1 = "/tmp/da_client1";
-    let socket2 = "/tmp/da_client2";
-
-    let _ = fs::remove_file(socket1);
-    let _ = fs::remove_file(socket2);
-
-    let listener1 = UnixListener::bind(socket1)?;
-    let listener2 = UnixListener::bind(socket2)?;
-
-    println!("Server waiting for connections...");
-
-    // Accept connections with timeout
-    let stream1 = match timeout(READ_TIMEOUT, listener1.accept()).await {
-        Ok(Ok((stream, _))) => stream,
-        Ok(Err(e)) => return Err(e.into()),
-        Err(_) => return Err(anyhow::anyhow!("Timeout waiting for client1 connection"))
-    };
-    
-    let stream2 = match timeout(READ_TIMEOUT, listener2.accept()).await {
-        Ok(Ok((stream, _))) => stream,
-        Ok(Err(e)) => return Err(e.into()),
-        Err(_) => return Err(anyhow::anyhow!("Timeout waiting for client2 connection"))
+async fn main() -> ExitCode {
+    logging::init();
+    let output = match Output::from_args() {
+        Ok(output) => output,
+        Err(e) => return error::exit(Err(e)),
     };
-    
-    println!("Both clients connected!");
-
-    let (mut read1, mut write1) = stream1.into_split();
-    let (mut read2, mut write2) = stream2.into_split();
-
-    // Generate random masks
-    let r0: i64 = rand::rng().random_range(1..PRIME_MOD);
-    let r1: i64 = rand::rng().random_range(1..PRIME_MOD);
-
-    println!("Generated masks: r0 = {}, r1 = {}", r0, r1);
-
-    // Initialize buffers and readers
-    let mut buf1 = String::new();
-    let mut buf2 = String::new();
-    let mut reader1 = BufReader::new(&mut read1);
-    let mut reader2 = BufReader::new(&mut read2);
-
-    println!("Reading shares from client1...");
-    match timeout(READ_TIMEOUT, reader1.read_line(&mut buf1)).await {
-        Ok(Ok(0)) => return Err(anyhow::anyhow!("Client1 disconnected")),
-        Ok(Ok(_)) => {
-            if buf1.trim().is_empty() {
-                return Err(anyhow::anyhow!("Client1 sent empty data"));
-            }
-        },
-        Ok(Err(e)) => return Err(e.into()),
-        Err(_) => return Err(anyhow::anyhow!("Timeout waiting for client1 data"))
-    }
-    
-    let x0: i64 = buf1.trim().parse()?;
-    buf1.clear();
-    
-    match timeout(READ_TIMEOUT, reader1.read_line(&mut buf1)).await {
-        Ok(Ok(0)) => return Err(anyhow::anyhow!("Client1 disconnected")),
-        Ok(Ok(_)) => {
-            if buf1.trim().is_empty() {
-                return Err(anyhow::anyhow!("Client1 sent empty data"));
-            }
-        },
-        Ok(Err(e)) => return Err(e.into()),
-        Err(_) => return Err(anyhow::anyhow!("Timeout waiting for client1 data"))
-    }
-    
-    let y0: i64 = buf1.trim().parse()?;
+    let mut report = Report::new("server");
+    let result = serve(&mut report).await;
+    error::exit(output.finish(report, result))
+}
 
-    println!("Reading shares from client2...");
-    match timeout(READ_TIMEOUT, reader2.read_line(&mut buf2)).await {
-        Ok(Ok(0)) => return Err(anyhow::anyhow!("Client2 disconnected")),
-        Ok(Ok(_)) => {
-            if buf2.trim().is_empty() {
-                return Err(anyhow::anyhow!("Client2 sent empty data"));
-            }
-        },
-        Ok(Err(e)) => return Err(e.into()),
-        Err(_) => return Err(anyhow::anyhow!("Timeout waiting for client2 data"))
-    }
-    
-    let x1: i64 = buf2.trim().parse()?;
-    buf2.clear();
-    
-    match timeout(READ_TIMEOUT, reader2.read_line(&mut buf2)).await {
-        Ok(Ok(0)) => return Err(anyhow::anyhow!("Client2 disconnected")),
-        Ok(Ok(_)) => {
-            if buf2.trim().is_empty() {
-                return Err(anyhow::anyhow!("Client2 sent empty data"));
-            }
-        },
-        Ok(Err(e)) => return Err(e.into()),
-        Err(_) => return Err(anyhow::anyhow!("Timeout waiting for client2 data"))
+async fn serve(report: &mut Report) -> anyhow::Result<()> {
+    let parties: usize = cli::parse_flag("--parties", 2)?;
+    if parties < 2 {
+        return Err(ProtocolError::InvalidInput(format!("At least two parties are required, got {}", parties)).into());
     }
-    
-    let y1: i64 = buf2.trim().parse()?;
-
-    println!("Received: x0={}, y0={}, x1={}, y1={}", x0, y0, x1, y1);
-
-
-    let x0_mod = x0 % PRIME_MOD;
-    let y0_mod = y0 % PRIME_MOD;
-    let x1_mod = x1 % PRIME_MOD;
-    let y1_mod = y1 % PRIME_MOD;
-    
-    // Compute masked values
-    let masked_x0 = (x0_mod + r0) % PRIME_MOD;
-    let masked_y0 = (y0_mod + r0) % PRIME_MOD;
-    let masked_x1 = (x1_mod + r1) % PRIME_MOD;
-    let masked_y1 = (y1_mod + r1) % PRIME_MOD;
 
-    // Send masked values to respective clients
-    println!("Sending masked values to clients...");
-    println!("Sending to client1: x0+r0={}, y0+r0={}", masked_x0, masked_y0);
-    match timeout(READ_TIMEOUT, write1.write_all(format!("{}\n{}\n", masked_x0, masked_y0).as_bytes())).await {
-        Ok(Ok(_)) => {},
-        Ok(Err(e)) => return Err(e.into()),
-        Err(_) => return Err(anyhow::anyhow!("Timeout sending data to client1"))
+    let mut listeners = Vec::with_capacity(parties);
+    for k in 1..=parties {
+        let socket = format!("/tmp/da_client{}", k);
+        let _ = fs::remove_file(&socket);
+        listeners.push(Arc::new(UnixListener::bind(&socket)?));
     }
-    
-    println!("Sending to client2: x1+r1={}, y1+r1={}", masked_x1, masked_y1);
-    match timeout(READ_TIMEOUT, write2.write_all(format!("{}\n{}\n", masked_x1, masked_y1).as_bytes())).await {
-        Ok(Ok(_)) => {},
-        Ok(Err(e)) => return Err(e.into()),
-        Err(_) => return Err(anyhow::anyhow!("Timeout sending data to client2"))
-    }
-
-    // Wait for clients to exchange data and send back the exchanged masked values
-    buf1.clear();
-    buf2.clear();
-    
-    println!("Waiting for exchanged masked values from client1...");
 
-    match timeout(READ_TIMEOUT, reader1.read_line(&mut buf1)).await {
-        Ok(Ok(0)) => return Err(anyhow::anyhow!("Client1 disconnected")),
-        Ok(Ok(_)) => {
-            if buf1.trim().is_empty() {
-                return Err(anyhow::anyhow!("Client1 sent empty data"));
-            }
-        },
-        Ok(Err(e)) => return Err(e.into()),
-        Err(_) => return Err(anyhow::anyhow!("Timeout waiting for client1 data"))
-    }
-    
-    let mx1_from_client1: i64 = buf1.trim().parse()?;
-    buf1.clear();
-    
-    match timeout(READ_TIMEOUT, reader1.read_line(&mut buf1)).await {
-        Ok(Ok(0)) => return Err(anyhow::anyhow!("Client1 disconnected")),
-        Ok(Ok(_)) => {
-            if buf1.trim().is_empty() {
-                return Err(anyhow::anyhow!("Client1 sent empty data"));
-            }
-        },
-        Ok(Err(e)) => return Err(e.into()),
-        Err(_) => return Err(anyhow::anyhow!("Timeout waiting for client1 data"))
+    let keyring = Keyring::from_args("server")?.map(Arc::new);
+    let grace = resume::grace_from_args()?;
+    let mut rng = randomness::from_args(randomness::SERVER_STREAM)?;
+
+    // In the star topology the clients reach each other only through our relay socket
+    let topology = Topology::from_args()?;
+    if let Topology::Star(relay) = &topology {
+        if keyring.is_none() {
+            return Err(ProtocolError::InvalidInput("The star topology needs --keys to encrypt the relayed peer link".into()).into());
+        }
+        let _ = fs::remove_file(relay);
+        tokio::spawn(rendezvous::relay(UnixListener::bind(relay)?));
     }
-    
-    let my1_from_client1: i64 = buf1.trim().parse()?;
 
-    println!("Waiting for exchanged masked values from client2...");
+    info!("Server waiting for {} connections...", parties);
 
-    match timeout(READ_TIMEOUT, reader2.read_line(&mut buf2)).await {
-        Ok(Ok(0)) => return Err(anyhow::anyhow!("Client2 disconnected")),
-        Ok(Ok(_)) => {
-            if buf2.trim().is_empty() {
-                return Err(anyhow::anyhow!("Client2 sent empty data"));
-            }
-        },
-        Ok(Err(e)) => return Err(e.into()),
-        Err(_) => return Err(anyhow::anyhow!("Timeout waiting for client2 data"))
-    }
-    
-    let mx0_from_client2: i64 = buf2.trim().parse()?;
-    buf2.clear();
-    
-    match timeout(READ_TIMEOUT, reader2.read_line(&mut buf2)).await {
-        Ok(Ok(0)) => return Err(anyhow::anyhow!("Client2 disconnected")),
-        Ok(Ok(_)) => {
-            if buf2.trim().is_empty() {
-                return Err(anyhow::anyhow!("Client2 sent empty data"));
-            }
-        },
-        Ok(Err(e)) => return Err(e.into()),
-        Err(_) => return Err(anyhow::anyhow!("Timeout waiting for client2 data"))
+    // Accept connections with timeout
+    let mut links = Vec::with_capacity(parties);
+    for (k, listener) in (1..=parties).zip(&listeners) {
+        let stream = match timeout(READ_TIMEOUT, listener.accept()).await {
+            Ok(Ok((stream, _))) => stream,
+            Ok(Err(e)) => return Err(e.into()),
+            Err(_) => return Err(ProtocolError::timeout(Phase::Accept, format!("client{}", k)).into())
+        };
+        let peer = format!("client{}", k);
+        let mut link = Link::from_unix(stream);
+        auth::authenticate(keyring.as_deref(), &mut link, &peer, rng.as_mut()).await?;
+        // A client that drops out may dial in again and pick up where it left off
+        let reconnect = resume::accept(listener.clone(), &peer, keyring.clone(), grace);
+        links.push(resume::resumable(link, &peer, reconnect));
     }
-    
-    let my0_from_client2: i64 = buf2.trim().parse()?;
-
 
-    // DU-ATALLAH MULTIPLICATION PROTOCOL IMPLEMENTATION
-    println!("=== DU-ATALLAH MULTIPLICATION PROTOCOL ===");
-    
-    
-    // Direct terms: x0*y0 and x1*y1 (server has direct access)
-    let term1 = (x0_mod * y0_mod) % PRIME_MOD;
-    let term4 = (x1_mod * y1_mod) % PRIME_MOD;
-    
-    
+    info!("All {} clients connected!", parties);
+    report.connected();
 
-    let recovered_y1 = ((my1_from_client1 - r1) % PRIME_MOD + PRIME_MOD) % PRIME_MOD;
-    
-    let recovered_y0 = ((my0_from_client2 - r0) % PRIME_MOD + PRIME_MOD) % PRIME_MOD;
-    
-    let term2 = (x0_mod * recovered_y1) % PRIME_MOD;
-    let term3 = (x1_mod * recovered_y0) % PRIME_MOD;
-   
-    let mut final_result = term1;
-    final_result = (final_result + term2) % PRIME_MOD;
-    final_result = (final_result + term3) % PRIME_MOD;
-    final_result = (final_result + term4) % PRIME_MOD;
-    
-    println!("Du-Atallah aggregation:");
-    println!("Du-Atallah result: {}", final_result);
-    
-    
+    let outcome = server::run(&mut links, &topology, rng.as_mut(), report).await?;
+    report.outcome(&outcome);
 
     Ok(())
 }
@@ -0,0 +1,57 @@
+use std::process::ExitCode;
+
+use du_attalah::cli;
+use du_attalah::error::{self, ProtocolError};
+use du_attalah::logging;
+use du_attalah::operation::Operation;
+use du_attalah::randomness;
+use du_attalah::report::{Output, Report};
+use du_attalah::simulate::simulate;
+
+/// Run a whole session in one process:
+/// `simulate <op> <client1 input> <client2 input>`, where `,` separates
+/// input lines, e.g. `simulate mul 3,4 5,6`.
+#[tokio::main]
+async fn main() -> ExitCode {
+    logging::init();
+    let output = match Output::from_args() {
+        Ok(output) => output,
+        Err(e) => return error::exit(Err(e)),
+    };
+    let mut report = Report::new("simulate");
+    let result = run(output, &mut report).await;
+    error::exit(output.finish(report, result))
+}
+
+async fn run(output: Output, report: &mut Report) -> anyhow::Result<()> {
+    let args = cli::positional();
+    let [op, input1, input2] = args.as_slice() else {
+        return Err(ProtocolError::InvalidInput("Usage: simulate <op> <client1 input> <client2 input> [--insecure-seed N] [--insecure-debug] [--output json]".into()).into());
+    };
+    let op: Operation = op.parse()?;
+    let input1 = input1.replace(',', "\n") + "\n";
+    let input2 = input2.replace(',', "\n") + "\n";
+
+    let rngs = [
+        randomness::from_args(randomness::SERVER_STREAM)?,
+        randomness::from_args(1)?,
+        randomness::from_args(2)?,
+    ];
+    let simulation = simulate(op, [&input1, &input2], rngs).await?;
+    if let Some(session) = simulation.reports[0].session {
+        report.joined(session, op);
+    }
+    report.result = simulation.reports[0].result.clone();
+    if output == Output::Json {
+        return Ok(());
+    }
+
+    println!();
+    println!("Transcript:");
+    for message in &simulation.transcript {
+        println!("{} -> {}: {}", message.from, message.to, logging::secret(&message.line));
+    }
+    println!("Outcome: {}", simulation.outcome);
+
+    Ok(())
+}
@@ -0,0 +1,129 @@
+//! Typed, labelled messaging with one peer.
+//!
+//! A [`Channel`] wraps a [`Link`] with the name of the peer at the other end
+//! and a deadline. Every message is one line: `send` writes anything that
+//! implements `Display`, `recv` parses the next line into anything that
+//! implements `FromStr`. Timeouts, hang-ups, malformed lines and aborts from
+//! the peer all come back as a [`ProtocolError`](crate::error::ProtocolError)
+//! naming the peer, so a new protocol phase is a few `send`/`recv` calls.
+
+use std::fmt;
+use std::str::FromStr;
+
+use tokio::time::Duration;
+use uuid::Uuid;
+use zeroize::Zeroizing;
+
+use crate::abort;
+use crate::du_atallah::Correlation;
+use crate::error::ProtocolError;
+use crate::resume;
+use crate::transport::Link;
+use crate::wire::{self, READ_TIMEOUT};
+
+/// Our end of a link to one named peer.
+pub struct Channel<'a> {
+    link: &'a mut Link,
+    peer: String,
+    deadline: Duration,
+}
+
+impl<'a> Channel<'a> {
+    /// A channel to `peer` over `link`, waiting up to [`READ_TIMEOUT`] per call.
+    pub fn new(link: &'a mut Link, peer: impl Into<String>) -> Self {
+        Channel { link, peer: peer.into(), deadline: READ_TIMEOUT }
+    }
+
+    /// Use `deadline` instead of [`READ_TIMEOUT`] for every call without one of its own.
+    pub fn with_deadline(mut self, deadline: Duration) -> Self {
+        self.deadline = deadline;
+        self
+    }
+
+    /// The peer's name, as used in errors.
+    pub fn peer(&self) -> &str {
+        &self.peer
+    }
+
+    /// Record that the link now carries `session`, see [`resume::joined`].
+    pub fn joined(&self, session: Uuid) {
+        resume::joined(&self.link.session, session);
+    }
+
+    /// Send `value` as one line.
+    pub async fn send<T: fmt::Display + ?Sized>(&mut self, value: &T) -> anyhow::Result<()> {
+        self.send_within(value, self.deadline).await
+    }
+
+    /// Send `value` as one line, giving up after `deadline`.
+    pub async fn send_within<T: fmt::Display + ?Sized>(&mut self, value: &T, deadline: Duration) -> anyhow::Result<()> {
+        let line = Zeroizing::new(value.to_string());
+        wire::write_line_within(&mut self.link.writer, &self.peer, &line, deadline).await
+    }
+
+    /// Receive the next line as a `T`.
+    pub async fn recv<T: FromStr>(&mut self) -> anyhow::Result<T>
+    where
+        T::Err: fmt::Display,
+    {
+        self.recv_within(self.deadline).await
+    }
+
+    /// Receive the next line as a `T`, waiting at most `deadline`.
+    pub async fn recv_within<T: FromStr>(&mut self, deadline: Duration) -> anyhow::Result<T>
+    where
+        T::Err: fmt::Display,
+    {
+        let line = Zeroizing::new(wire::read_line_within(&mut self.link.reader, &self.peer, deadline).await?);
+        abort::check(&line)?;
+        wire::parse(&line, &self.peer)
+    }
+
+    /// Send field elements as one space-separated line.
+    pub async fn send_values(&mut self, values: &[u64]) -> anyhow::Result<()> {
+        let line = Zeroizing::new(values.iter().map(u64::to_string).collect::<Vec<_>>().join(" "));
+        self.send(line.as_str()).await
+    }
+
+    /// Receive a line of space-separated field elements.
+    pub async fn recv_values(&mut self) -> anyhow::Result<Vec<u64>> {
+        let line: Zeroizing<String> = Zeroizing::new(self.recv().await?);
+        line.split_whitespace().map(|v| wire::parse(v, &self.peer)).collect()
+    }
+
+    /// Receive a line of exactly `count` field elements.
+    pub async fn recv_exactly(&mut self, count: usize) -> anyhow::Result<Vec<u64>> {
+        let values = self.recv_values().await?;
+        if values.len() != count {
+            return Err(ProtocolError::Malformed(format!("{} sent {} values, expected {}", self.peer, values.len(), count)).into());
+        }
+        Ok(values)
+    }
+
+    /// Send a batch of correlations as a line of masks followed by a line of offsets.
+    pub async fn send_correlations(&mut self, correlations: &[Correlation]) -> anyhow::Result<()> {
+        let masks: Vec<u64> = correlations.iter().map(|c| c.mask).collect();
+        let offsets: Vec<u64> = correlations.iter().map(|c| c.offset).collect();
+        self.send_values(&masks).await?;
+        self.send_values(&offsets).await
+    }
+
+    /// Receive a batch of exactly `count` correlations sent with `send_correlations`.
+    pub async fn recv_correlations(&mut self, count: usize) -> anyhow::Result<Vec<Correlation>> {
+        let masks = self.recv_values().await?;
+        let offsets = self.recv_values().await?;
+        if masks.len() != count || offsets.len() != count {
+            return Err(ProtocolError::Malformed(format!("{} sent {} correlations, expected {}", self.peer, masks.len(), count)).into());
+        }
+        Ok(masks
+            .into_iter()
+            .zip(offsets)
+            .map(|(mask, offset)| Correlation { mask, offset })
+            .collect())
+    }
+
+    /// Tell the peer that `me` is aborting the session because of `error`, see [`abort::notify`].
+    pub async fn abort(&mut self, me: &str, error: &anyhow::Error) {
+        abort::notify(&mut self.link.writer, &self.peer, me, error).await;
+    }
+}
@@ -0,0 +1,70 @@
+//! Minimal command-line flag parsing shared by the binaries.
+
+use std::str::FromStr;
+
+use crate::error::ProtocolError;
+
+/// Switches that take no value; every other `--flag` is followed by one.
+const SWITCHES: &[&str] = &["--from-owner", "--insecure-debug"];
+
+/// Value following `--name` on the command line, if present.
+pub fn flag(name: &str) -> Option<String> {
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == name {
+            return args.next();
+        }
+    }
+    None
+}
+
+/// Whether the boolean switch `--name` was passed.
+pub fn has_flag(name: &str) -> bool {
+    std::env::args().skip(1).any(|arg| arg == name)
+}
+
+/// The command-line arguments that are neither flags nor flag values, in order.
+pub fn positional() -> Vec<String> {
+    positional_in(std::env::args().skip(1))
+}
+
+/// [`positional`] over `args`, which excludes the program name.
+pub fn positional_in(args: impl IntoIterator<Item = String>) -> Vec<String> {
+    let mut positional = Vec::new();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if SWITCHES.contains(&arg.as_str()) {
+            continue;
+        }
+        if arg.starts_with("--") {
+            args.next();
+            continue;
+        }
+        positional.push(arg);
+    }
+    positional
+}
+
+/// Show an interactive input prompt: on stdout normally, on stderr with
+/// `--output json` so stdout carries only the JSON report.
+pub fn prompt(text: &str) {
+    if flag("--output").as_deref() == Some("json") {
+        eprintln!("{}", text);
+    } else {
+        println!("{}", text);
+    }
+}
+
+/// Parse the value of `--name`, falling back to `default` when the flag is absent.
+pub fn parse_flag<T>(name: &str, default: T) -> anyhow::Result<T>
+where
+    T: FromStr,
+    T::Err: std::fmt::Display,
+{
+    match flag(name) {
+        Some(value) => value
+            .parse()
+            .map_err(|e| ProtocolError::InvalidInput(format!("Invalid value for {}: {}", name, e)).into()),
+        None => Ok(default),
+    }
+}
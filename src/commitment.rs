@@ -0,0 +1,98 @@
+//! Commit-then-reveal for the values parties exchange with each other.
+//!
+//! Without it, whoever reads first sees the peer's masked values before
+//! choosing its own. Instead each side first sends a SHA-256 commitment over
+//! the session, its name, a fresh 32-byte nonce and the values, and only
+//! reveals the nonce and values once it holds the peer's commitment. A reveal
+//! that does not match the commitment aborts the session, so every party is
+//! bound to what it sends before it learns anything from the exchange.
+
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::channel::Channel;
+use crate::error::ProtocolError;
+use crate::randomness::RandomSource;
+
+const CONTEXT: &str = "du-atallah commitment v1";
+
+/// The nonce and values behind a commitment, sent as the reveal.
+pub struct Opening {
+    nonce: [u8; 32],
+    values: Vec<u64>,
+}
+
+impl Opening {
+    /// The reveal line: the hex nonce followed by the values.
+    pub fn to_line(&self) -> String {
+        let values: Vec<String> = self.values.iter().map(u64::to_string).collect();
+        format!("{} {}", hex::encode(self.nonce), values.join(" "))
+    }
+
+    pub fn from_line(peer: &str, line: &str) -> anyhow::Result<Self> {
+        let mut parts = line.split_whitespace();
+        let nonce = parts
+            .next()
+            .and_then(|n| hex::decode(n).ok())
+            .and_then(|b| b.try_into().ok())
+            .ok_or_else(|| ProtocolError::Malformed(format!("{} sent a malformed reveal", peer)))?;
+        let values = parts
+            .map(|v| v.parse())
+            .collect::<Result<_, _>>()
+            .map_err(|e| ProtocolError::Malformed(format!("{} sent a malformed reveal: {}", peer, e)))?;
+        Ok(Opening { nonce, values })
+    }
+
+    pub fn values(&self) -> &[u64] {
+        &self.values
+    }
+}
+
+fn digest(session: Uuid, from: &str, nonce: &[u8; 32], values: &[u64]) -> String {
+    let mut hash = Sha256::new();
+    hash.update(format!("{}\n{}\n{}\n", CONTEXT, session, from));
+    hash.update(nonce);
+    for v in values {
+        hash.update(v.to_be_bytes());
+    }
+    hex::encode(hash.finalize())
+}
+
+/// Commit `from` to `values` in `session`, returning the commitment to send
+/// now and the opening to reveal later.
+pub fn commit(session: Uuid, from: &str, values: &[u64], rng: &mut dyn RandomSource) -> (String, Opening) {
+    let mut nonce = [0u8; 32];
+    rng.fill_bytes(&mut nonce);
+    (digest(session, from, &nonce, values), Opening { nonce, values: values.to_vec() })
+}
+
+/// Whether `opening` is what `from` committed to with `commitment`.
+pub fn verify(commitment: &str, session: Uuid, from: &str, opening: &Opening) -> bool {
+    digest(session, from, &opening.nonce, &opening.values) == commitment
+}
+
+/// Check a reveal from `peer` against its commitment and return the values.
+pub fn open(commitment: &str, session: Uuid, peer: &str, reveal: &str) -> anyhow::Result<Vec<u64>> {
+    let opening = Opening::from_line(peer, reveal)?;
+    if !verify(commitment, session, peer, &opening) {
+        return Err(ProtocolError::Aborted(format!("{} revealed values that do not match its commitment, session aborted", peer)).into());
+    }
+    Ok(opening.values)
+}
+
+/// Swap `values` with the peer on `channel`: commitments first, then the reveals.
+pub async fn exchange(
+    channel: &mut Channel<'_>,
+    session: Uuid,
+    me: &str,
+    values: &[u64],
+    rng: &mut dyn RandomSource,
+) -> anyhow::Result<Vec<u64>> {
+    let (commitment, opening) = commit(session, me, values, rng);
+    channel.send(&commitment).await?;
+    let peer_commitment: String = channel.recv().await?;
+
+    channel.send(&opening.to_line()).await?;
+    let reveal: String = channel.recv().await?;
+    open(&peer_commitment, session, channel.peer(), &reveal)
+}
@@ -0,0 +1,66 @@
+use std::fmt;
+
+use crate::field::Field;
+use crate::randomness::RandomSource;
+
+/// Correlated randomness handed to one client by the server for a single
+/// Du-Atallah product. For a product `x * y` client1 receives `(Ra, ra)`,
+/// client2 receives `(Rb, rb)`, and the server guarantees `ra + rb = Ra * Rb`.
+#[derive(PartialEq, Eq)]
+pub struct Correlation {
+    pub mask: u64,
+    pub offset: u64,
+}
+
+impl fmt::Debug for Correlation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Correlation { mask: <redacted>, offset: <redacted> }")
+    }
+}
+
+/// Deal `count` independent correlations, returning client1's and client2's halves.
+pub fn deal<R: RandomSource + ?Sized>(field: &Field, rng: &mut R, count: usize) -> (Vec<Correlation>, Vec<Correlation>) {
+    let mut client1 = Vec::with_capacity(count);
+    let mut client2 = Vec::with_capacity(count);
+
+    for _ in 0..count {
+        let ra_mask = rng.field_element(field);
+        let rb_mask = rng.field_element(field);
+        let ra = rng.field_element(field);
+        let rb = field.sub(field.mul(ra_mask, rb_mask), ra);
+
+        client1.push(Correlation { mask: ra_mask, offset: ra });
+        client2.push(Correlation { mask: rb_mask, offset: rb });
+    }
+
+    (client1, client2)
+}
+
+/// Mask each value with the matching correlation before sending it to the peer.
+pub fn mask(field: &Field, values: &[u64], correlations: &[Correlation]) -> Vec<u64> {
+    values
+        .iter()
+        .zip(correlations)
+        .map(|(&v, c)| field.add(v, c.mask))
+        .collect()
+}
+
+/// Client1's additive share of `sum(x[i] * y[i])`, given client2's masked `y + Rb`.
+pub fn client1_share(field: &Field, x: &[u64], masked_y: &[u64], correlations: &[Correlation]) -> u64 {
+    field.sum(
+        x.iter()
+            .zip(masked_y)
+            .zip(correlations)
+            .map(|((&xi, &my), c)| field.add(field.mul(xi, my), c.offset)),
+    )
+}
+
+/// Client2's additive share of `sum(x[i] * y[i])`, given client1's masked `x + Ra`.
+pub fn client2_share(field: &Field, masked_x: &[u64], correlations: &[Correlation]) -> u64 {
+    field.sum(
+        masked_x
+            .iter()
+            .zip(correlations)
+            .map(|(&mx, c)| field.sub(c.offset, field.mul(mx, c.mask))),
+    )
+}
@@ -0,0 +1,143 @@
+//! Authenticated encryption for a link once its handshake has agreed keys.
+//!
+//! Every line a role writes is sealed with ChaCha20-Poly1305 under the key for
+//! its direction and sent as a frame: a 4-byte big-endian length followed by
+//! the ciphertext. Nonces are a per-direction counter, so frames cannot be
+//! replayed, reordered or dropped without the receiver noticing. The roles keep
+//! reading and writing plain lines: the writer seals each line as it is
+//! written, and a background task opens incoming frames.
+
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use tokio::io::{AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tracing::warn;
+
+use crate::transport::{Link, Reader, Writer};
+
+/// Largest frame accepted from a peer.
+const MAX_FRAME: usize = 16 << 20;
+
+/// Capacity of the plaintext pipe from a link's decrypting task to the role.
+const PIPE_BUFFER: usize = 1 << 20;
+
+/// Wrap `link` so that everything sent on it is sealed with `send_key` and
+/// everything received is opened with `recv_key`.
+pub fn wrap(link: Link, peer: &str, send_key: &[u8; 32], recv_key: &[u8; 32]) -> Link {
+    let (role_end, task_end) = tokio::io::duplex(PIPE_BUFFER);
+    tokio::spawn(open(link.reader, task_end, ChaCha20Poly1305::new(Key::from_slice(recv_key)), peer.to_string()));
+
+    Link {
+        reader: Box::new(BufReader::new(role_end)),
+        writer: Box::new(Sealer {
+            inner: link.writer,
+            cipher: ChaCha20Poly1305::new(Key::from_slice(send_key)),
+            counter: 0,
+            line: Vec::new(),
+            frames: Vec::new(),
+        }),
+        session: link.session,
+    }
+}
+
+/// The nonce for the `counter`-th frame in one direction.
+fn nonce(counter: u64) -> Nonce {
+    let mut bytes = [0u8; 12];
+    bytes[4..].copy_from_slice(&counter.to_be_bytes());
+    *Nonce::from_slice(&bytes)
+}
+
+/// Writer that seals every completed line into a frame and passes the frames
+/// on to `inner`. Frames are written out eagerly, so a role that exits right
+/// after its last write does not lose it.
+struct Sealer {
+    inner: Writer,
+    cipher: ChaCha20Poly1305,
+    counter: u64,
+    line: Vec<u8>,
+    frames: Vec<u8>,
+}
+
+impl Sealer {
+    /// Write as much of the pending frames to `inner` as it accepts.
+    fn poll_drain(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while !self.frames.is_empty() {
+            match Pin::new(&mut self.inner).poll_write(cx, &self.frames) {
+                Poll::Ready(Ok(0)) => return Poll::Ready(Err(io::ErrorKind::WriteZero.into())),
+                Poll::Ready(Ok(n)) => {
+                    self.frames.drain(..n);
+                },
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncWrite for Sealer {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        if this.frames.len() >= MAX_FRAME {
+            std::task::ready!(this.poll_drain(cx))?;
+        }
+
+        this.line.extend_from_slice(buf);
+        while let Some(end) = this.line.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = this.line.drain(..=end).collect();
+            let frame = this
+                .cipher
+                .encrypt(&nonce(this.counter), line.as_slice())
+                .map_err(|_| io::Error::other("encryption failed"))?;
+            this.counter += 1;
+            this.frames.extend_from_slice(&(frame.len() as u32).to_be_bytes());
+            this.frames.extend_from_slice(&frame);
+        }
+
+        if let Poll::Ready(Err(e)) = this.poll_drain(cx) {
+            return Poll::Ready(Err(e));
+        }
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        std::task::ready!(this.poll_drain(cx))?;
+        Pin::new(&mut this.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        std::task::ready!(this.poll_drain(cx))?;
+        Pin::new(&mut this.inner).poll_shutdown(cx)
+    }
+}
+
+/// Decrypt frames from `peer` and pass the lines on to the role. A frame that
+/// fails to decrypt ends the link, which the role sees as a disconnect.
+async fn open(mut reader: Reader, mut plain: impl AsyncWriteExt + Unpin, cipher: ChaCha20Poly1305, peer: String) {
+    let mut counter = 0u64;
+    while let Ok(len) = reader.read_u32().await {
+        let len = len as usize;
+        if len > MAX_FRAME {
+            warn!("{} sent an oversized frame ({} bytes)", peer, len);
+            break;
+        }
+        let mut frame = vec![0u8; len];
+        if reader.read_exact(&mut frame).await.is_err() {
+            break;
+        }
+        let Ok(line) = cipher.decrypt(&nonce(counter), frame.as_slice()) else {
+            warn!("Decryption of data from {} failed: frame was tampered with or replayed", peer);
+            break;
+        };
+        counter += 1;
+        if plain.write_all(&line).await.is_err() {
+            break;
+        }
+    }
+    let _ = plain.shutdown().await;
+}
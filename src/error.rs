@@ -0,0 +1,110 @@
+//! Typed protocol failures and the exit codes they map to.
+//!
+//! Roles return `anyhow::Result`, but every failure that a peer or the user can
+//! cause is a [`ProtocolError`], so callers can tell a timeout from a cheating
+//! peer with [`protocol_error`] instead of matching on message text, and each
+//! binary exits with the code of the kind of failure ([`exit`]).
+
+use std::fmt;
+use std::process::ExitCode;
+
+/// What a role was doing when a peer stopped answering.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    /// Dialing the peer's socket.
+    Connect,
+    /// Waiting for the peer to dial in.
+    Accept,
+    /// Waiting for the peer's next line.
+    Read,
+    /// Sending a line to the peer.
+    Write,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProtocolError {
+    /// `peer` did not answer within the protocol timeout.
+    Timeout { phase: Phase, peer: String },
+    /// `peer` closed the connection mid-session.
+    Disconnected { peer: String },
+    /// A peer sent something that does not parse or has the wrong shape.
+    Malformed(String),
+    /// The user's input or command line cannot be used.
+    InvalidInput(String),
+    /// A check caught a peer cheating.
+    Aborted(String),
+    /// `origin` failed with `reason` and aborted the session, as passed on by a peer.
+    PeerAborted { origin: String, reason: String },
+    /// A peer could not prove its identity.
+    AuthFailed(String),
+}
+
+impl ProtocolError {
+    pub fn timeout(phase: Phase, peer: impl Into<String>) -> Self {
+        ProtocolError::Timeout { phase, peer: peer.into() }
+    }
+
+    pub fn disconnected(peer: impl Into<String>) -> Self {
+        ProtocolError::Disconnected { peer: peer.into() }
+    }
+
+    /// Short name of the variant, as reported by `--output json`.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ProtocolError::Timeout { .. } => "timeout",
+            ProtocolError::Disconnected { .. } => "disconnected",
+            ProtocolError::Malformed(_) => "malformed",
+            ProtocolError::InvalidInput(_) => "invalid_input",
+            ProtocolError::Aborted(_) | ProtocolError::PeerAborted { .. } => "aborted",
+            ProtocolError::AuthFailed(_) => "auth_failed",
+        }
+    }
+
+    /// Process exit code for this failure. 1 is left for everything else.
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            ProtocolError::InvalidInput(_) => 2,
+            ProtocolError::Malformed(_) => 3,
+            ProtocolError::Disconnected { .. } => 4,
+            ProtocolError::Timeout { .. } => 5,
+            ProtocolError::Aborted(_) | ProtocolError::PeerAborted { .. } => 6,
+            ProtocolError::AuthFailed(_) => 7,
+        }
+    }
+}
+
+impl fmt::Display for ProtocolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProtocolError::Timeout { phase: Phase::Connect, peer } => write!(f, "Timeout connecting to {}", peer),
+            ProtocolError::Timeout { phase: Phase::Accept, peer } => write!(f, "Timeout waiting for {} connection", peer),
+            ProtocolError::Timeout { phase: Phase::Read, peer } => write!(f, "Timeout waiting for {} data", peer),
+            ProtocolError::Timeout { phase: Phase::Write, peer } => write!(f, "Timeout sending data to {}", peer),
+            ProtocolError::Disconnected { peer } => write!(f, "{} disconnected", peer),
+            ProtocolError::PeerAborted { origin, reason } => write!(f, "Session aborted by {}: {}", origin, reason),
+            ProtocolError::Malformed(message)
+            | ProtocolError::InvalidInput(message)
+            | ProtocolError::Aborted(message)
+            | ProtocolError::AuthFailed(message) => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for ProtocolError {}
+
+/// The [`ProtocolError`] behind `error`, looking through any context added on the way up.
+pub fn protocol_error(error: &anyhow::Error) -> Option<&ProtocolError> {
+    error.chain().find_map(|e| e.downcast_ref::<ProtocolError>())
+}
+
+/// Turn a binary's result into its exit status, printing the error like
+/// returning it from `main` would.
+pub fn exit(result: anyhow::Result<()>) -> ExitCode {
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {:?}", e);
+            ExitCode::from(protocol_error(&e).map_or(1, ProtocolError::exit_code))
+        }
+    }
+}
@@ -0,0 +1,87 @@
+use crate::error::ProtocolError;
+
+/// Default prime modulus used by the Du-Atallah protocol.
+pub const PRIME_MOD: u64 = 1_000_000_007;
+
+/// The Mersenne prime 2^61 - 1, for computations whose results outgrow `PRIME_MOD`.
+pub const MERSENNE_61: u64 = (1 << 61) - 1;
+
+/// Arithmetic modulo a prime. Intermediate products are computed in `u128`
+/// so any modulus below 2^64 is safe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Field {
+    modulus: u64,
+}
+
+impl Field {
+    pub const fn new(modulus: u64) -> Self {
+        Field { modulus }
+    }
+
+    /// A field for a modulus that came from outside the program, rejecting
+    /// moduli below 2, for which there is nothing to reduce into.
+    pub fn try_new(modulus: u64) -> anyhow::Result<Self> {
+        if modulus < 2 {
+            return Err(ProtocolError::InvalidInput(format!("Modulus must be at least 2, got {}", modulus)).into());
+        }
+        Ok(Field::new(modulus))
+    }
+
+    pub fn modulus(&self) -> u64 {
+        self.modulus
+    }
+
+    pub fn reduce(&self, value: u64) -> u64 {
+        value % self.modulus
+    }
+
+    pub fn reduce_i64(&self, value: i64) -> u64 {
+        (value as i128).rem_euclid(self.modulus as i128) as u64
+    }
+
+    pub fn add(&self, a: u64, b: u64) -> u64 {
+        ((a as u128 + b as u128) % self.modulus as u128) as u64
+    }
+
+    pub fn sub(&self, a: u64, b: u64) -> u64 {
+        self.add(a, self.neg(b))
+    }
+
+    pub fn neg(&self, a: u64) -> u64 {
+        let a = self.reduce(a);
+        if a == 0 { 0 } else { self.modulus - a }
+    }
+
+    pub fn mul(&self, a: u64, b: u64) -> u64 {
+        ((a as u128 * b as u128) % self.modulus as u128) as u64
+    }
+
+    pub fn pow(&self, base: u64, mut exp: u64) -> u64 {
+        let mut result = self.reduce(1);
+        let mut base = self.reduce(base);
+        while exp > 0 {
+            if exp & 1 == 1 {
+                result = self.mul(result, base);
+            }
+            base = self.mul(base, base);
+            exp >>= 1;
+        }
+        result
+    }
+
+    /// Multiplicative inverse by Fermat's little theorem; `None` for zero.
+    pub fn inv(&self, a: u64) -> Option<u64> {
+        let a = self.reduce(a);
+        if a == 0 { None } else { Some(self.pow(a, self.modulus - 2)) }
+    }
+
+    pub fn sum(&self, values: impl IntoIterator<Item = u64>) -> u64 {
+        values.into_iter().fold(0, |acc, v| self.add(acc, v))
+    }
+}
+
+impl Default for Field {
+    fn default() -> Self {
+        Field::new(PRIME_MOD)
+    }
+}
@@ -0,0 +1,44 @@
+//! Where a client reads its private inputs: the terminal, or a data owner that
+//! split its values with `owner share` and sent this client one share of each.
+
+use tokio::io::{AsyncBufRead, BufReader};
+use tokio::net::UnixListener;
+use tokio::time::timeout;
+use tracing::info;
+use std::fs;
+
+use crate::auth::{self, Keyring};
+use crate::cli;
+use crate::error::{Phase, ProtocolError};
+use crate::randomness::RandomSource;
+use crate::transport::Link;
+use crate::wire::READ_TIMEOUT;
+
+pub type Input = Box<dyn AsyncBufRead + Unpin + Send>;
+
+/// Socket on which client `k` receives its shares from a data owner.
+pub fn owner_socket(k: usize) -> String {
+    format!("/tmp/da_owner_client{}", k)
+}
+
+/// Stdin, or with `--from-owner` the shares a data owner sends to client `k`.
+/// The owner writes them in the same line format a user would type, after
+/// authenticating as `owner` when `keyring` is set.
+pub async fn open(k: usize, keyring: Option<&Keyring>, rng: &mut dyn RandomSource) -> anyhow::Result<Input> {
+    if !cli::has_flag("--from-owner") {
+        return Ok(Box::new(BufReader::new(tokio::io::stdin())));
+    }
+
+    let socket = owner_socket(k);
+    let _ = fs::remove_file(&socket);
+    let listener = UnixListener::bind(&socket)?;
+    info!("Client{}: Waiting for shares from data owner at {}", k, socket);
+    let (stream, _) = match timeout(READ_TIMEOUT, listener.accept()).await {
+        Ok(Ok(stream)) => stream,
+        Ok(Err(e)) => return Err(e.into()),
+        Err(_) => return Err(ProtocolError::timeout(Phase::Accept, "data owner").into())
+    };
+    let mut link = Link::from_unix(stream);
+    auth::authenticate(keyring, &mut link, "owner", rng).await?;
+    Ok(link.reader)
+}
@@ -0,0 +1,31 @@
+//! Shared building blocks for the Du-Atallah server and clients.
+
+pub mod abort;
+pub mod additive;
+pub mod auth;
+pub mod channel;
+pub mod cli;
+pub mod commitment;
+pub mod du_atallah;
+pub mod encryption;
+pub mod error;
+pub mod field;
+pub mod input;
+pub mod logging;
+pub mod nparty;
+pub mod operation;
+pub mod poly;
+pub mod randomness;
+pub mod relay;
+pub mod rendezvous;
+pub mod report;
+pub mod resume;
+pub mod roles;
+pub mod secret;
+pub mod shamir;
+pub mod share_file;
+pub mod simulate;
+pub mod spdz;
+pub mod stats;
+pub mod transport;
+pub mod wire;
@@ -0,0 +1,72 @@
+//! Structured logging shared by the binaries.
+//!
+//! Roles log through `tracing`, inside a `session` span carrying the session ID
+//! and role. [`init`] writes events to stdout, or to stderr with `--output json`
+//! so the JSON report is alone on stdout, at the level set by `RUST_LOG`
+//! (default `info`). Inputs, masks, masked values and shares are logged through
+//! [`secret`], which prints `<redacted>` unless the process was started with
+//! `--insecure-debug` or `DA_INSECURE_DEBUG=1`.
+
+use std::fmt;
+use std::io::IsTerminal;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use tracing_subscriber::EnvFilter;
+
+use crate::cli;
+
+/// Environment variable that reveals secret-bearing log fields, like `--insecure-debug`.
+pub const INSECURE_DEBUG_ENV: &str = "DA_INSECURE_DEBUG";
+
+static REVEAL_SECRETS: AtomicBool = AtomicBool::new(false);
+
+/// Install the subscriber and decide whether secrets are revealed.
+pub fn init() {
+    let subscriber = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")))
+        .with_target(false);
+    if cli::flag("--output").as_deref() == Some("json") {
+        subscriber.with_writer(std::io::stderr).with_ansi(std::io::stderr().is_terminal()).init();
+    } else {
+        subscriber.with_ansi(std::io::stdout().is_terminal()).init();
+    }
+
+    let reveal = cli::has_flag("--insecure-debug") || std::env::var(INSECURE_DEBUG_ENV).is_ok_and(|v| v == "1");
+    if reveal {
+        REVEAL_SECRETS.store(true, Ordering::Relaxed);
+        tracing::warn!("Insecure debug logging is enabled: inputs, masks and shares are logged in the clear");
+    }
+}
+
+/// Whether secret-bearing fields are logged in the clear.
+pub fn reveals_secrets() -> bool {
+    REVEAL_SECRETS.load(Ordering::Relaxed)
+}
+
+/// A logged value that is only shown with `--insecure-debug`.
+pub struct Redacted<T>(T);
+
+/// Wrap a secret-bearing value for logging, e.g. `debug!(x0 = %secret(x0), ...)`.
+pub fn secret<T>(value: T) -> Redacted<T> {
+    Redacted(value)
+}
+
+impl<T: fmt::Display> fmt::Display for Redacted<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if reveals_secrets() {
+            self.0.fmt(f)
+        } else {
+            f.write_str("<redacted>")
+        }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Redacted<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if reveals_secrets() {
+            self.0.fmt(f)
+        } else {
+            f.write_str("<redacted>")
+        }
+    }
+}
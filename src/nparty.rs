@@ -0,0 +1,47 @@
+//! Multiplication for `n` data owners holding additive shares
+//! `x = sum(x[i])` and `y = sum(y[i])`. Expanding the product gives
+//!
+//! `x * y = sum(x[i] * y[i]) + sum over i < j of (x[i] * y[j] + x[j] * y[i])`
+//!
+//! The diagonal terms are local. Each unordered pair `i < j` computes its two
+//! cross terms as a single length-2 Du-Atallah inner product in which party `i`
+//! plays client1 with `[x[i], y[i]]` and party `j` plays client2 with
+//! `[y[j], x[j]]`. Parties are numbered from 1, matching the socket names.
+
+use crate::du_atallah::{self, Correlation};
+use crate::field::Field;
+
+/// Number of Du-Atallah products dealt per pair of parties.
+pub const PRODUCTS_PER_PAIR: usize = 2;
+
+/// Every pair `(i, j)` with `1 <= i < j <= parties`.
+pub fn pairs(parties: usize) -> impl Iterator<Item = (usize, usize)> {
+    (1..=parties).flat_map(move |i| (i + 1..=parties).map(move |j| (i, j)))
+}
+
+/// The vector party `me` contributes to its cross-term product with `peer`.
+pub fn cross_vector(me: usize, peer: usize, x: u64, y: u64) -> [u64; 2] {
+    if me < peer { [x, y] } else { [y, x] }
+}
+
+/// Party `me`'s share of `x[me] * y[peer] + x[peer] * y[me]`.
+pub fn cross_share(
+    field: &Field,
+    me: usize,
+    peer: usize,
+    x: u64,
+    y: u64,
+    peer_masked: &[u64],
+    correlations: &[Correlation],
+) -> u64 {
+    if me < peer {
+        du_atallah::client1_share(field, &cross_vector(me, peer, x, y), peer_masked, correlations)
+    } else {
+        du_atallah::client2_share(field, peer_masked, correlations)
+    }
+}
+
+/// Party's final additive share of `x * y`: its diagonal term plus all its cross-term shares.
+pub fn output_share(field: &Field, x: u64, y: u64, cross_shares: &[u64]) -> u64 {
+    field.add(field.mul(x, y), field.sum(cross_shares.iter().copied()))
+}
@@ -0,0 +1,93 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Serialize, Serializer};
+
+use crate::cli;
+use crate::error::ProtocolError;
+
+/// Computation a session runs. Each client announces it to the server as the
+/// first line on its connection so both sides of the run agree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    /// `(x0 + x1) * (y0 + y1)` over the clients' additive shares.
+    Multiply,
+    /// `P(x)` where client1 holds the coefficients and client2 holds `x`.
+    PolyEval,
+    /// Sum, mean and variance of a dataset additively shared between the clients.
+    Stats,
+    /// `(x1 + ... + xn) * (y1 + ... + yn)` for any number of parties, run with the `party` binary.
+    MultiplyN,
+    /// Same product as `MultiplyN` over Shamir `(t, n)` shares, opened from any `t` parties.
+    ShamirMultiply,
+    /// Same product as `Multiply` with SPDZ-style MACs, so a cheating client aborts the session.
+    MaliciousMultiply,
+}
+
+impl Operation {
+    /// Parse the operation from the positional command-line arguments, see
+    /// [`Operation::from_positional`].
+    pub fn from_args() -> anyhow::Result<Self> {
+        Self::from_positional(&cli::positional())
+    }
+
+    /// The operation named by the only positional argument, `Multiply` when
+    /// there is none. Any further positional argument is rejected.
+    pub fn from_positional(args: &[String]) -> anyhow::Result<Self> {
+        match args {
+            [] => Ok(Operation::Multiply),
+            [op] => op.parse(),
+            [_, extra, ..] => Err(ProtocolError::InvalidInput(format!("Unexpected argument: {}", extra)).into()),
+        }
+    }
+
+    /// Whether the operation runs between any number of `party` processes
+    /// rather than the fixed client1/client2 pair.
+    pub fn is_multiparty(&self) -> bool {
+        matches!(self, Operation::MultiplyN | Operation::ShamirMultiply)
+    }
+
+    /// Whether the clients end the session without an output share of their own.
+    pub fn opens_at_server(&self) -> bool {
+        matches!(self, Operation::Multiply | Operation::MaliciousMultiply)
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Operation::Multiply => "mul",
+            Operation::PolyEval => "poly",
+            Operation::Stats => "stats",
+            Operation::MultiplyN => "nmul",
+            Operation::ShamirMultiply => "smul",
+            Operation::MaliciousMultiply => "mmul",
+        }
+    }
+}
+
+impl FromStr for Operation {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "mul" => Ok(Operation::Multiply),
+            "poly" => Ok(Operation::PolyEval),
+            "stats" => Ok(Operation::Stats),
+            "nmul" => Ok(Operation::MultiplyN),
+            "smul" => Ok(Operation::ShamirMultiply),
+            "mmul" => Ok(Operation::MaliciousMultiply),
+            other => Err(ProtocolError::InvalidInput(format!("Unknown operation: {}", other)).into()),
+        }
+    }
+}
+
+impl Serialize for Operation {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
@@ -0,0 +1,27 @@
+//! Private polynomial evaluation: client1 holds the coefficients of `P`,
+//! client2 holds the point `x`, and the two end up with additive shares of
+//! `P(x)`. Client2 expands `x` into its power vector in one batch, after which
+//! `P(x)` is the inner product of the coefficients with those powers and is
+//! computed with a batch of Du-Atallah products.
+
+use crate::field::Field;
+
+/// `[1, x, x^2, ..., x^(count-1)]`, computed with one multiplication per power.
+pub fn powers(field: &Field, x: u64, count: usize) -> Vec<u64> {
+    let x = field.reduce(x);
+    let mut out = Vec::with_capacity(count);
+    let mut current = field.reduce(1);
+    for _ in 0..count {
+        out.push(current);
+        current = field.mul(current, x);
+    }
+    out
+}
+
+/// Evaluate `P(x)` in the clear with Horner's rule, coefficients in ascending degree.
+pub fn evaluate(field: &Field, coefficients: &[u64], x: u64) -> u64 {
+    coefficients
+        .iter()
+        .rev()
+        .fold(0, |acc, &c| field.add(field.mul(acc, x), c))
+}
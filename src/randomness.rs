@@ -0,0 +1,128 @@
+//! The single place protocol randomness comes from. Every mask, correlation,
+//! share and session ID is drawn through `RandomSource`, so the generator can
+//! be swapped and the one in use is named in the logs.
+
+use rand::rngs::StdRng;
+use rand::{Rng, RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::cli;
+use crate::error::ProtocolError;
+use crate::field::Field;
+
+/// Environment variable that, like `--insecure-seed`, switches to `SeededRandom`.
+pub const SEED_ENV: &str = "DA_INSECURE_SEED";
+
+/// Stream the server draws from in seeded mode; party `k` uses stream `k`.
+pub const SERVER_STREAM: u64 = 0;
+
+/// Stream a data owner draws from in seeded mode.
+pub const OWNER_STREAM: u64 = u64::MAX;
+
+/// Source of uniformly random field elements.
+pub trait RandomSource: Send {
+    /// Short description of the generator, printed when a party starts.
+    fn name(&self) -> &'static str;
+
+    /// A uniform element of `field`, zero included.
+    fn field_element(&mut self, field: &Field) -> u64;
+
+    /// `count` independent uniform elements of `field`.
+    fn field_elements(&mut self, field: &Field, count: usize) -> Vec<u64> {
+        (0..count).map(|_| self.field_element(field)).collect()
+    }
+
+    /// Fill `dest` with random bytes.
+    fn fill_bytes(&mut self, dest: &mut [u8]);
+
+    /// A random version 4 UUID, used as a session ID.
+    fn uuid(&mut self) -> Uuid {
+        let mut bytes = [0u8; 16];
+        self.fill_bytes(&mut bytes);
+        uuid::Builder::from_random_bytes(bytes).into_uuid()
+    }
+}
+
+/// ChaCha-based CSPRNG seeded from the operating system.
+pub struct OsRandom {
+    rng: StdRng,
+}
+
+impl OsRandom {
+    pub fn new() -> Self {
+        OsRandom { rng: StdRng::from_os_rng() }
+    }
+}
+
+impl Default for OsRandom {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RandomSource for OsRandom {
+    fn name(&self) -> &'static str {
+        "os-seeded ChaCha CSPRNG"
+    }
+
+    fn field_element(&mut self, field: &Field) -> u64 {
+        self.rng.random_range(0..field.modulus())
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.rng.fill_bytes(dest);
+    }
+}
+
+/// ChaCha20 seeded from a fixed number. **Insecure**: anyone who knows the
+/// seed can recompute every mask. It exists so a whole run, transcript
+/// included, can be replayed for debugging and golden-transcript tests.
+/// Each role draws from its own stream so parties sharing a seed do not
+/// reuse each other's randomness.
+pub struct SeededRandom {
+    rng: ChaCha20Rng,
+}
+
+impl SeededRandom {
+    pub fn new(seed: u64, stream: u64) -> Self {
+        let mut rng = ChaCha20Rng::seed_from_u64(seed);
+        rng.set_stream(stream);
+        SeededRandom { rng }
+    }
+}
+
+impl RandomSource for SeededRandom {
+    fn name(&self) -> &'static str {
+        "INSECURE seeded ChaCha20 (reproducible runs only)"
+    }
+
+    fn field_element(&mut self, field: &Field) -> u64 {
+        self.rng.random_range(0..field.modulus())
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.rng.fill_bytes(dest);
+    }
+}
+
+/// `SeededRandom` on `stream` when a seed is given with `--insecure-seed` or
+/// `DA_INSECURE_SEED`, otherwise `OsRandom`.
+pub fn from_args(stream: u64) -> anyhow::Result<Box<dyn RandomSource>> {
+    let seed = match cli::flag("--insecure-seed") {
+        Some(seed) => Some(seed),
+        None => std::env::var(SEED_ENV).ok(),
+    };
+    match seed {
+        Some(seed) => {
+            let seed: u64 = seed
+                .trim()
+                .parse()
+                .map_err(|e| ProtocolError::InvalidInput(format!("Invalid insecure seed {}: {}", seed, e)))?;
+            warn!("Seeded randomness is enabled. Masks are predictable; never use this with real data.");
+            Ok(Box::new(SeededRandom::new(seed, stream)))
+        },
+        None => Ok(Box::new(OsRandom::new())),
+    }
+}
@@ -0,0 +1,97 @@
+//! Signatures over the masked values the clients relay in `mul`.
+//!
+//! In `mul` each client forwards its masked values to the other, which passes
+//! them back to the server. Every client signs what it forwards with a key it
+//! generates for the session; the server learns the public half directly from
+//! the client and hands it to the peer. The peer refuses values whose signature
+//! does not verify, so an honest forwarder cannot be framed, and it cannot alter
+//! them on the way to the server without breaking the signature. When a relay
+//! differs from what the server sent, a bad signature means the relayer
+//! tampered and a good one means the originator forwarded the wrong values.
+//!
+//! Before forwarding, each client sends a commitment to its signed values and
+//! waits for the peer's, so neither sees the other's masked values before it is
+//! bound to its own.
+
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::auth::Identity;
+use crate::error::ProtocolError;
+
+const CONTEXT: &str = "du-atallah relay v1";
+const COMMITMENT: &str = "du-atallah relay commitment v1";
+
+/// What `from` signs when forwarding `values` in `session`.
+fn message(session: Uuid, from: &str, values: &[u64]) -> String {
+    let values: Vec<String> = values.iter().map(u64::to_string).collect();
+    format!("{}\n{}\n{}\n{}", CONTEXT, session, from, values.join(" "))
+}
+
+/// Hex signature by `identity` over the `values` it forwards in `session`.
+pub fn sign(identity: &Identity, session: Uuid, values: &[u64]) -> String {
+    hex::encode(identity.sign(message(session, identity.name(), values).as_bytes()).to_bytes())
+}
+
+/// Whether `signature` is `from`'s signature over `values` in `session`.
+pub fn verify(key: &VerifyingKey, session: Uuid, from: &str, values: &[u64], signature: &str) -> bool {
+    hex::decode(signature)
+        .ok()
+        .and_then(|b| Signature::from_slice(&b).ok())
+        .is_some_and(|s| key.verify(message(session, from, values).as_bytes(), &s).is_ok())
+}
+
+/// `from`'s commitment to forwarding `values` with `signature` in `session`.
+/// Only `from` holds the session key, so the signature hides the values from
+/// the peer like a fresh nonce until the signed values are revealed.
+pub fn commit(session: Uuid, from: &str, values: &[u64], signature: &str) -> String {
+    let mut hash = Sha256::new();
+    hash.update(format!("{}\n{}\n{}", COMMITMENT, message(session, from, values), signature));
+    hex::encode(hash.finalize())
+}
+
+/// Check that `from` revealed the values and signature it committed to, failing the session if not.
+pub fn open(commitment: &str, session: Uuid, from: &str, values: &[u64], signature: &str) -> anyhow::Result<()> {
+    if commit(session, from, values, signature) != commitment {
+        return Err(ProtocolError::Aborted(format!("{} revealed masked values that do not match its commitment, session aborted", from)).into());
+    }
+    Ok(())
+}
+
+/// Parse a session public key sent as hex by `peer`.
+pub fn parse_key(peer: &str, line: &str) -> anyhow::Result<VerifyingKey> {
+    hex::decode(line)
+        .ok()
+        .and_then(|b| <[u8; 32]>::try_from(b).ok())
+        .and_then(|b| VerifyingKey::from_bytes(&b).ok())
+        .ok_or_else(|| ProtocolError::Malformed(format!("{} sent a malformed session key", peer)).into())
+}
+
+/// Check that `relayer` passed on exactly the values the server `sent` to
+/// `origin`, naming the party that misbehaved if not.
+pub fn check(
+    session: Uuid,
+    origin: &str,
+    relayer: &str,
+    origin_key: &VerifyingKey,
+    sent: &[u64],
+    relayed: &[u64],
+    signature: &str,
+) -> anyhow::Result<()> {
+    if !verify(origin_key, session, origin, relayed, signature) {
+        return Err(ProtocolError::Aborted(format!(
+            "Relay check failed: {} tampered with the masked values from {}, session aborted",
+            relayer,
+            origin
+        )).into());
+    }
+    if sent != relayed {
+        return Err(ProtocolError::Aborted(format!(
+            "Relay check failed: {} forwarded masked values other than the server's to {}, session aborted",
+            origin,
+            relayer
+        )).into());
+    }
+    Ok(())
+}
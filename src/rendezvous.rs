@@ -0,0 +1,312 @@
+//! How the two clients find each other, whichever of them starts first.
+//!
+//! Each client binds its own peer socket before it connects to the server and
+//! registers the address with `listening <address>` once it has joined the
+//! session. The server waits for both registrations and answers each client
+//! with a [`Pairing`]: the client that registered first is told to `accept`,
+//! the other to `dial` the first one's address. Both sockets exist before
+//! either client joins, so the dial never races the bind and neither client
+//! has to be started before the other.
+//!
+//! In the star [`Topology`] the clients never connect to each other: the server
+//! tells both to `relay` through its relay socket, each with a [`Ticket`]
+//! naming the session and the client. A client sends its ticket first on every
+//! relayed connection, and the relay splices client1's connection to client2's
+//! for the same session only. The clients run the `--keys` handshake over the
+//! spliced connection, so the server forwards frames it cannot read or alter.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::io::{self, AsyncReadExt};
+use tokio::net::{UnixListener, UnixStream};
+use tokio::time::{timeout, Instant};
+use tracing::{debug, info, warn};
+use uuid::Uuid;
+
+use crate::auth::{self, Keyring};
+use crate::channel::Channel;
+use crate::cli;
+use crate::error::{Phase, ProtocolError};
+use crate::randomness::RandomSource;
+use crate::resume;
+use crate::transport::Link;
+use crate::wire::{self, READ_TIMEOUT};
+
+/// The server's socket for relayed peer links in the star topology.
+pub const RELAY_SOCKET: &str = "/tmp/da_relay";
+
+/// Environment variable that selects the topology when `--topology` is absent.
+pub const TOPOLOGY_ENV: &str = "DA_TOPOLOGY";
+
+/// How traffic between the two clients travels.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Topology {
+    /// The clients connect to each other.
+    Direct,
+    /// The clients connect to the server's relay socket at this address.
+    Star(String),
+}
+
+impl Topology {
+    /// The topology chosen with `--topology direct|star` or `DA_TOPOLOGY`, direct by default.
+    pub fn from_args() -> anyhow::Result<Self> {
+        let topology = match cli::flag("--topology") {
+            Some(topology) => Some(topology),
+            None => std::env::var(TOPOLOGY_ENV).ok(),
+        };
+        match topology.as_deref().map(str::trim) {
+            None | Some("direct") => Ok(Topology::Direct),
+            Some("star") => Ok(Topology::Star(RELAY_SOCKET.into())),
+            Some(other) => Err(ProtocolError::InvalidInput(format!("Unknown topology {}, expected direct or star", other)).into()),
+        }
+    }
+}
+
+/// A client's registration: the address its peer socket listens on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Listening(pub String);
+
+impl fmt::Display for Listening {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "listening {}", self.0)
+    }
+}
+
+impl FromStr for Listening {
+    type Err = String;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        match line.strip_prefix("listening ") {
+            Some(address) if !address.is_empty() => Ok(Listening(address.to_string())),
+            _ => Err("expected `listening <address>`".into()),
+        }
+    }
+}
+
+/// What a client sends first on a relayed connection: its session and which
+/// client it is, as `relay <session> <client>`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Ticket {
+    pub session: Uuid,
+    pub client: usize,
+}
+
+impl fmt::Display for Ticket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "relay {} {}", self.session, self.client)
+    }
+}
+
+impl FromStr for Ticket {
+    type Err = String;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let ["relay", session, client] = line.split(' ').collect::<Vec<_>>()[..] else {
+            return Err("expected `relay <session> <client>`".into());
+        };
+        let session = session.parse().map_err(|e| format!("session: {}", e))?;
+        match client.parse() {
+            Ok(client @ (1 | 2)) => Ok(Ticket { session, client }),
+            _ => Err(format!("client must be 1 or 2, got {}", client)),
+        }
+    }
+}
+
+/// How the server tells a client to open its peer link.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Pairing {
+    /// Wait for the peer on our own socket.
+    Accept,
+    /// Connect to the peer's socket at this address.
+    Dial(String),
+    /// Connect to the server's relay socket at this address and present the ticket.
+    Relay(String, Ticket),
+}
+
+impl fmt::Display for Pairing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Pairing::Accept => write!(f, "accept"),
+            Pairing::Dial(address) => write!(f, "dial {}", address),
+            Pairing::Relay(address, ticket) => write!(f, "{} {}", ticket, address),
+        }
+    }
+}
+
+impl FromStr for Pairing {
+    type Err = String;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let usage = || "expected `accept`, `dial <address>` or `relay <session> <client> <address>`".to_string();
+        match line.split_once(' ') {
+            None if line == "accept" => Ok(Pairing::Accept),
+            Some(("dial", address)) if !address.is_empty() => Ok(Pairing::Dial(address.to_string())),
+            Some(("relay", _)) => {
+                let [tag, session, client, address] = line.splitn(4, ' ').collect::<Vec<_>>()[..] else {
+                    return Err(usage());
+                };
+                let ticket = [tag, session, client].join(" ").parse()?;
+                Ok(Pairing::Relay(address.to_string(), ticket))
+            },
+            _ => Err(usage()),
+        }
+    }
+}
+
+/// The peer socket of client `k`.
+pub fn socket(k: usize) -> String {
+    format!("/tmp/p2p_client{}", k)
+}
+
+/// Bind client `k`'s peer socket, replacing one left behind by an earlier run.
+pub fn listen(k: usize) -> anyhow::Result<Arc<UnixListener>> {
+    let socket = socket(k);
+    let _ = fs::remove_file(&socket);
+    Ok(Arc::new(UnixListener::bind(&socket)?))
+}
+
+/// Client side: register `address` with the server and learn how to reach the peer.
+pub async fn pair(server: &mut Channel<'_>, address: &str) -> anyhow::Result<Pairing> {
+    server.send(&Listening(address.to_string())).await?;
+    server.recv().await
+}
+
+/// Server side: collect both registrations and tell the later client to dial
+/// the earlier one, or both to go through the relay for `session` in the star
+/// topology.
+pub async fn broker(client1: &mut Channel<'_>, client2: &mut Channel<'_>, session: Uuid, topology: &Topology) -> anyhow::Result<()> {
+    let (first, second) = tokio::join!(registration(client1), registration(client2));
+    let ((address1, at1), (address2, at2)) = (first?, second?);
+    if let Topology::Star(relay) = topology {
+        info!("Relaying peer traffic through {}", relay);
+        client1.send(&Pairing::Relay(relay.clone(), Ticket { session, client: 1 })).await?;
+        client2.send(&Pairing::Relay(relay.clone(), Ticket { session, client: 2 })).await
+    } else if at2 < at1 {
+        info!("Client1 dials Client2");
+        client2.send(&Pairing::Accept).await?;
+        client1.send(&Pairing::Dial(address2)).await
+    } else {
+        info!("Client2 dials Client1");
+        client1.send(&Pairing::Accept).await?;
+        client2.send(&Pairing::Dial(address1)).await
+    }
+}
+
+/// A client's registered address and when it arrived.
+async fn registration(client: &mut Channel<'_>) -> anyhow::Result<(String, Instant)> {
+    let Listening(address) = client.recv().await?;
+    Ok((address, Instant::now()))
+}
+
+/// Open the resumable link to `peer` the way `pairing` says, accepting on
+/// our own `listener`, dialing the peer's socket or dialing the relay and
+/// presenting our ticket. A relayed link needs `keyring`, since only the
+/// handshake keeps the server out.
+pub async fn open(
+    pairing: Pairing,
+    listener: Arc<UnixListener>,
+    peer: &str,
+    keyring: Option<Arc<Keyring>>,
+    grace: Duration,
+    rng: &mut dyn RandomSource,
+) -> anyhow::Result<Link> {
+    let (stream, reconnect) = match pairing {
+        Pairing::Accept => match timeout(READ_TIMEOUT, listener.accept()).await {
+            Ok(accepted) => (accepted?.0, resume::accept(listener, peer, keyring.clone(), grace)),
+            Err(_) => return Err(ProtocolError::timeout(Phase::Accept, peer).into()),
+        },
+        Pairing::Relay(..) if keyring.is_none() => {
+            return Err(ProtocolError::InvalidInput("The star topology needs --keys to encrypt the relayed peer link".into()).into());
+        },
+        Pairing::Dial(address) => match timeout(READ_TIMEOUT, UnixStream::connect(&address)).await {
+            Ok(connected) => (connected?, resume::dial(address, peer, keyring.clone(), grace)),
+            Err(_) => return Err(ProtocolError::timeout(Phase::Connect, peer).into()),
+        },
+        Pairing::Relay(address, ticket) => match timeout(READ_TIMEOUT, UnixStream::connect(&address)).await {
+            Ok(connected) => {
+                let mut stream = connected?;
+                wire::write_line(&mut stream, "relay", &ticket.to_string()).await?;
+                (stream, resume::dial_with(address, Some(ticket.to_string()), peer, keyring.clone(), grace))
+            },
+            Err(_) => return Err(ProtocolError::timeout(Phase::Connect, peer).into()),
+        },
+    };
+    let mut link = Link::from_unix(stream);
+    auth::authenticate(keyring.as_deref(), &mut link, peer, rng).await?;
+    Ok(resume::resumable(link, peer, reconnect))
+}
+
+/// Connections on the relay socket waiting for their other half, by session.
+type Waiting = Arc<Mutex<HashMap<Uuid, (usize, UnixStream)>>>;
+
+/// Longest ticket line the relay reads before giving up on a connection.
+const TICKET_LIMIT: usize = 128;
+
+/// Splice client1's and client2's connections on the relay socket together
+/// once both have presented a ticket for the same session. A connection that
+/// arrives for a half already waiting replaces it, so a pair that breaks is
+/// rebuilt from the next two connections and relayed links resume like direct
+/// ones.
+pub async fn relay(listener: UnixListener) {
+    let waiting = Waiting::default();
+    loop {
+        let stream = match listener.accept().await {
+            Ok((stream, _)) => stream,
+            Err(e) => {
+                warn!("Relay could not accept a connection: {}", e);
+                continue;
+            },
+        };
+        let waiting = waiting.clone();
+        tokio::spawn(async move {
+            if let Err(e) = admit(stream, &waiting).await {
+                warn!("Relay dropped a connection: {:#}", e);
+            }
+        });
+    }
+}
+
+/// Read `stream`'s ticket and pair it with the waiting half of its session, or leave it waiting.
+async fn admit(mut stream: UnixStream, waiting: &Waiting) -> anyhow::Result<()> {
+    let ticket = match timeout(READ_TIMEOUT, read_ticket(&mut stream)).await {
+        Ok(ticket) => ticket?,
+        Err(_) => return Err(ProtocolError::timeout(Phase::Read, "relay").into()),
+    };
+    let other = {
+        let mut waiting = waiting.lock().unwrap();
+        match waiting.remove(&ticket.session) {
+            Some((client, other)) if client != ticket.client => Some(other),
+            stale => {
+                if stale.is_some() {
+                    debug!("Client{} reconnected to the relay for session {}", ticket.client, ticket.session);
+                }
+                waiting.insert(ticket.session, (ticket.client, stream));
+                return Ok(());
+            },
+        }
+    };
+    if let Some(mut other) = other {
+        info!("Relaying session {}", ticket.session);
+        let _ = io::copy_bidirectional(&mut stream, &mut other).await;
+    }
+    Ok(())
+}
+
+/// Read the ticket line one byte at a time, so no frame behind it is consumed.
+async fn read_ticket(stream: &mut UnixStream) -> anyhow::Result<Ticket> {
+    let mut line = Vec::new();
+    loop {
+        match stream.read_u8().await? {
+            b'\n' => break,
+            _ if line.len() >= TICKET_LIMIT => return Err(ProtocolError::Malformed("relay ticket is too long".into()).into()),
+            byte => line.push(byte),
+        }
+    }
+    let line = String::from_utf8_lossy(&line);
+    line.trim().parse().map_err(|e| ProtocolError::Malformed(format!("relay ticket: {}", e)).into())
+}
@@ -0,0 +1,171 @@
+//! Machine-readable session summaries for `--output json`.
+//!
+//! Each binary fills in one [`Report`] while it runs: the roles record the
+//! session they joined, and the binary adds what the session produced. With
+//! `--output json` the report is printed on stdout as a single JSON document
+//! once the process is done, whether the session succeeded or not, and all log
+//! output moves to stderr so stdout carries nothing else.
+
+use std::time::Instant;
+
+use serde::Serialize;
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::cli;
+use crate::error::{self, ProtocolError};
+use crate::operation::Operation;
+use crate::roles::server::Outcome;
+
+/// How a binary presents the end of a session on stdout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Output {
+    /// Human-readable log lines only.
+    Text,
+    /// One JSON [`Report`] per session.
+    Json,
+}
+
+impl Output {
+    /// The mode chosen with `--output text|json`, `text` by default.
+    pub fn from_args() -> anyhow::Result<Self> {
+        match cli::flag("--output").as_deref() {
+            None | Some("text") => Ok(Output::Text),
+            Some("json") => Ok(Output::Json),
+            Some(other) => Err(ProtocolError::InvalidInput(format!("Unknown output format: {} (expected text or json)", other)).into()),
+        }
+    }
+
+    /// Finish `report` with `result` and print it in JSON mode, handing the
+    /// result back so the binary still exits with the error.
+    pub fn finish<T>(self, mut report: Report, result: anyhow::Result<T>) -> anyhow::Result<T> {
+        report.finish(&result);
+        if self == Output::Json {
+            println!("{}", serde_json::to_string(&report)?);
+        }
+        result
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Ok,
+    Error,
+}
+
+/// Milliseconds spent connecting, in the session itself, and overall.
+#[derive(Debug, Default, Serialize)]
+pub struct Timings {
+    pub connect_ms: Option<u64>,
+    pub session_ms: Option<u64>,
+    pub total_ms: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ErrorReport {
+    pub kind: &'static str,
+    pub message: String,
+}
+
+/// Summary of one session from one role's point of view.
+#[derive(Debug, Serialize)]
+pub struct Report {
+    pub session: Option<Uuid>,
+    pub role: String,
+    pub operation: Option<Operation>,
+    pub status: Status,
+    /// The value the server or `owner reconstruct` opened.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<serde_json::Value>,
+    /// The output share a client or party kept, as `--share-file` would write it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub share: Option<Vec<u64>>,
+    /// The key files `keygen` wrote.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub files: Option<Vec<String>>,
+    pub timings: Timings,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<ErrorReport>,
+    #[serde(skip)]
+    started: Instant,
+    #[serde(skip)]
+    connected: Option<Instant>,
+}
+
+impl Report {
+    pub fn new(role: &str) -> Self {
+        Report {
+            session: None,
+            role: role.to_string(),
+            operation: None,
+            status: Status::Ok,
+            result: None,
+            share: None,
+            files: None,
+            timings: Timings::default(),
+            error: None,
+            started: Instant::now(),
+            connected: None,
+        }
+    }
+
+    /// Mark the end of the connection phase.
+    pub fn connected(&mut self) {
+        self.connected = Some(Instant::now());
+    }
+
+    /// Record the session this role joined.
+    pub fn joined(&mut self, session: Uuid, op: Operation) {
+        self.session = Some(session);
+        self.operation = Some(op);
+    }
+
+    /// Record what the server opened.
+    pub fn outcome(&mut self, outcome: &Outcome) {
+        self.result = Some(match outcome {
+            Outcome::Value(v) => json!(v),
+            Outcome::Statistics(s) => json!({
+                "count": s.count,
+                "sum": s.sum,
+                "mean": s.mean(),
+                "variance": s.variance(),
+            }),
+        });
+    }
+
+    /// Record what `owner reconstruct` opened from the share files: a single
+    /// value as a number, several as an array.
+    pub fn reconstructed(&mut self, values: &[u64]) {
+        self.result = Some(match values {
+            [v] => json!(v),
+            _ => json!(values),
+        });
+    }
+
+    fn finish<T>(&mut self, result: &anyhow::Result<T>) {
+        let now = Instant::now();
+        let ms = |since: Instant| now.duration_since(since).as_millis() as u64;
+        self.timings = Timings {
+            connect_ms: self.connected.map(|c| c.duration_since(self.started).as_millis() as u64),
+            session_ms: self.connected.map(ms),
+            total_ms: ms(self.started),
+        };
+        if let Err(e) = result {
+            self.status = Status::Error;
+            self.error = Some(ErrorReport { kind: error_kind(e), message: format!("{:#}", e) });
+        }
+    }
+}
+
+/// Classification of `error` for scripts: the [`ProtocolError`] kind, or
+/// `io` and `other` for failures outside the protocol.
+fn error_kind(error: &anyhow::Error) -> &'static str {
+    if let Some(e) = error::protocol_error(error) {
+        e.kind()
+    } else if error.downcast_ref::<std::io::Error>().is_some() {
+        "io"
+    } else {
+        "other"
+    }
+}
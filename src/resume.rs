@@ -0,0 +1,319 @@
+//! Links that survive a dropped socket.
+//!
+//! Each end of a resumable link keeps every line it sends for the rest of the
+//! session and counts the complete lines it has received; that count is its
+//! checkpoint. When the socket fails after the session has been joined, the end
+//! that dialed dials again and the end that listened accepts again, within the
+//! grace period. The new socket goes through the `--keys` handshake like the
+//! first one, both ends send `resume <session> <checkpoint>`, and each resends
+//! every line past the other's checkpoint. The roles above only see one slow
+//! read or write, so a client carries on from where it was without being asked
+//! for its inputs again. If the peer does not come back in time, the link reads
+//! as closed and the role fails with `disconnected` as before.
+
+use std::future::Future;
+use std::io;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::{Arc, OnceLock};
+use std::task::{ready, Context, Poll};
+use std::time::Duration;
+
+use tokio::io::{AsyncRead, AsyncWrite, BufReader, ReadBuf};
+use tokio::net::{UnixListener, UnixStream};
+use tokio::time::{sleep, timeout, Instant};
+use tracing::{info, warn};
+use uuid::Uuid;
+use zeroize::Zeroizing;
+
+use crate::auth::{self, Keyring};
+use crate::cli;
+use crate::error::{Phase, ProtocolError};
+use crate::randomness::OsRandom;
+use crate::transport::Link;
+use crate::wire;
+
+/// How long a peer has to reconnect, unless `--grace-period SECS` says otherwise.
+pub const GRACE_PERIOD: Duration = Duration::from_secs(10);
+
+/// Pause between attempts to dial a peer that is not listening yet.
+const REDIAL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Where a resumable link learns the session it belongs to.
+pub type SessionSlot = Arc<OnceLock<Uuid>>;
+
+type BoxFuture<T> = Pin<Box<dyn Future<Output = T> + Send>>;
+
+/// Opens a fresh, authenticated connection to the same peer.
+pub type Reconnect = Box<dyn Fn() -> BoxFuture<anyhow::Result<Link>> + Send + Sync>;
+
+/// The grace period chosen with `--grace-period SECS`.
+pub fn grace_from_args() -> anyhow::Result<Duration> {
+    Ok(Duration::from_secs(cli::parse_flag("--grace-period", GRACE_PERIOD.as_secs())?))
+}
+
+/// Record that a link carries `session`; a resumable link can be resumed from now on.
+pub fn joined(slot: &Option<SessionSlot>, session: Uuid) {
+    if let Some(slot) = slot {
+        let _ = slot.set(session);
+    }
+}
+
+/// Reconnect by dialing `socket` until `grace` runs out, then authenticating `peer`.
+pub fn dial(socket: impl Into<PathBuf>, peer: &str, keyring: Option<Arc<Keyring>>, grace: Duration) -> Reconnect {
+    dial_with(socket, None, peer, keyring, grace)
+}
+
+/// Like [`dial`], but send `greeting` on every new connection before the
+/// handshake, the way a relayed link announces itself to the relay.
+pub fn dial_with(socket: impl Into<PathBuf>, greeting: Option<String>, peer: &str, keyring: Option<Arc<Keyring>>, grace: Duration) -> Reconnect {
+    let (socket, peer) = (socket.into(), peer.to_string());
+    Box::new(move || {
+        let (socket, greeting, peer, keyring) = (socket.clone(), greeting.clone(), peer.clone(), keyring.clone());
+        Box::pin(async move {
+            let deadline = Instant::now() + grace;
+            let stream = loop {
+                match UnixStream::connect(&socket).await {
+                    Ok(stream) => break stream,
+                    Err(_) if Instant::now() < deadline => sleep(REDIAL_INTERVAL).await,
+                    Err(_) => return Err(ProtocolError::timeout(Phase::Connect, peer).into()),
+                }
+            };
+            let mut link = Link::from_unix(stream);
+            if let Some(greeting) = &greeting {
+                wire::write_line(&mut link.writer, "relay", greeting).await?;
+            }
+            auth::authenticate(keyring.as_deref(), &mut link, &peer, &mut OsRandom::new()).await?;
+            Ok(link)
+        })
+    })
+}
+
+/// Reconnect by accepting `peer`'s next connection on `listener` within `grace`.
+pub fn accept(listener: Arc<UnixListener>, peer: &str, keyring: Option<Arc<Keyring>>, grace: Duration) -> Reconnect {
+    let peer = peer.to_string();
+    Box::new(move || {
+        let (listener, peer, keyring) = (listener.clone(), peer.clone(), keyring.clone());
+        Box::pin(async move {
+            let (stream, _) = match timeout(grace, listener.accept()).await {
+                Ok(accepted) => accepted?,
+                Err(_) => return Err(ProtocolError::timeout(Phase::Accept, peer).into()),
+            };
+            let mut link = Link::from_unix(stream);
+            auth::authenticate(keyring.as_deref(), &mut link, &peer, &mut OsRandom::new()).await?;
+            Ok(link)
+        })
+    })
+}
+
+/// Make `link` to `peer` resumable through `reconnect` once its session is joined.
+pub fn resumable(link: Link, peer: &str, reconnect: Reconnect) -> Link {
+    let session = SessionSlot::default();
+    let (read, write) = tokio::io::split(Resumable {
+        peer: peer.to_string(),
+        link: Some(link),
+        reconnect,
+        resuming: None,
+        session: session.clone(),
+        closed: false,
+        sent: Vec::new(),
+        line: Zeroizing::new(Vec::new()),
+        outgoing: Zeroizing::new(Vec::new()),
+        written: 0,
+        incoming: Zeroizing::new(Vec::new()),
+        received: 0,
+    });
+    Link {
+        reader: Box::new(BufReader::new(read)),
+        writer: Box::new(write),
+        session: Some(session),
+    }
+}
+
+/// Both directions of a resumable link. Only complete lines are passed up or
+/// sent, so a line cut off by a failing socket is simply sent again.
+struct Resumable {
+    peer: String,
+    /// The current connection, `None` while resuming or once closed.
+    link: Option<Link>,
+    reconnect: Reconnect,
+    /// The reconnect and resume handshake in progress, yielding the new
+    /// connection and the peer's checkpoint.
+    resuming: Option<BoxFuture<anyhow::Result<(Link, usize)>>>,
+    session: SessionSlot,
+    /// Set once the link cannot be resumed; it then reads as closed.
+    closed: bool,
+    /// Every complete line sent this session, in case the peer needs it again.
+    sent: Vec<Zeroizing<Vec<u8>>>,
+    /// The line being written, until its newline arrives.
+    line: Zeroizing<Vec<u8>>,
+    /// Complete lines for the current connection, `written` bytes of which it took.
+    outgoing: Zeroizing<Vec<u8>>,
+    written: usize,
+    /// Bytes read but not passed up yet.
+    incoming: Zeroizing<Vec<u8>>,
+    /// Complete lines received: our checkpoint.
+    received: usize,
+}
+
+impl Resumable {
+    /// The connection failed: start resuming if the session is known, or
+    /// close the link for good and return false.
+    fn lost(&mut self) -> bool {
+        self.link = None;
+        let complete = self.incoming.iter().rposition(|&b| b == b'\n').map_or(0, |end| end + 1);
+        self.incoming.truncate(complete);
+
+        let session = match self.session.get() {
+            Some(&session) if !self.closed => session,
+            _ => {
+                self.closed = true;
+                return false;
+            },
+        };
+        warn!("Lost the connection to {}, waiting for it to resume session {}", self.peer, session);
+        let reconnect = (self.reconnect)();
+        let (peer, checkpoint) = (self.peer.clone(), self.received);
+        self.resuming = Some(Box::pin(async move {
+            let mut link = reconnect.await?;
+            wire::write_line(&mut link.writer, &peer, &format!("resume {} {}", session, checkpoint)).await?;
+            let reply = wire::read_line(&mut link.reader, &peer).await?;
+            let (theirs, their_checkpoint) = reply
+                .strip_prefix("resume ")
+                .and_then(|r| r.split_once(' '))
+                .ok_or_else(|| ProtocolError::Malformed(format!("{} sent a malformed resume request", peer)))?;
+            let theirs: Uuid = wire::parse(theirs, &peer)?;
+            if theirs != session {
+                return Err(ProtocolError::Aborted(format!("{} tried to resume session {}, expected {}", peer, theirs, session)).into());
+            }
+            Ok((link, wire::parse(their_checkpoint, &peer)?))
+        }));
+        true
+    }
+
+    /// Drive a resume in progress. Ready(true) once there is a connection,
+    /// Ready(false) if the link is closed.
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<bool> {
+        if let Some(resuming) = self.resuming.as_mut() {
+            let result = ready!(resuming.as_mut().poll(cx));
+            self.resuming = None;
+            if let Err(e) = result.and_then(|(link, checkpoint)| self.resumed(link, checkpoint)) {
+                warn!("Could not resume the link to {}: {:#}", self.peer, e);
+                self.closed = true;
+            }
+        }
+        Poll::Ready(self.link.is_some())
+    }
+
+    /// Take `link` into use and queue every line past the peer's `checkpoint`.
+    fn resumed(&mut self, link: Link, checkpoint: usize) -> anyhow::Result<()> {
+        let missed = self.sent.get(checkpoint..).ok_or_else(|| {
+            ProtocolError::Malformed(format!("{} claims {} lines, only {} were sent", self.peer, checkpoint, self.sent.len()))
+        })?;
+        self.outgoing = Zeroizing::new(missed.iter().flat_map(|line| line.iter().copied()).collect());
+        self.written = 0;
+        info!("Resumed the link to {}, resending {} lines", self.peer, missed.len());
+        self.link = Some(link);
+        Ok(())
+    }
+
+    /// Write out the queued lines, resuming the connection if it fails.
+    fn poll_send(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        loop {
+            if !ready!(self.poll_ready(cx)) {
+                return Poll::Ready(Err(io::ErrorKind::BrokenPipe.into()));
+            }
+            let link = self.link.as_mut().expect("poll_ready returned a connection");
+            if self.written == self.outgoing.len() {
+                match ready!(Pin::new(&mut link.writer).poll_flush(cx)) {
+                    Ok(()) => {
+                        self.outgoing.clear();
+                        self.written = 0;
+                        return Poll::Ready(Ok(()));
+                    },
+                    Err(e) if !self.lost() => return Poll::Ready(Err(e)),
+                    Err(_) => continue,
+                }
+            }
+            match ready!(Pin::new(&mut link.writer).poll_write(cx, &self.outgoing[self.written..])) {
+                Ok(0) if !self.lost() => return Poll::Ready(Err(io::ErrorKind::WriteZero.into())),
+                Err(e) if !self.lost() => return Poll::Ready(Err(e)),
+                Ok(n) => self.written += n,
+                Err(_) => {},
+            }
+        }
+    }
+}
+
+impl AsyncRead for Resumable {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        loop {
+            if let Some(end) = this.incoming.iter().rposition(|&b| b == b'\n') {
+                let n = (end + 1).min(buf.remaining());
+                buf.put_slice(&this.incoming[..n]);
+                this.incoming.drain(..n);
+                return Poll::Ready(Ok(()));
+            }
+            // Lines written without a flush, or owed after a resume, go out
+            // before we wait for the answer. If they cannot, the link is closed.
+            let _ = this.poll_send(cx);
+            if !ready!(this.poll_ready(cx)) {
+                return Poll::Ready(Ok(()));
+            }
+            let link = this.link.as_mut().expect("poll_ready returned a connection");
+            let mut chunk = Zeroizing::new([0u8; 4096]);
+            let mut chunk_buf = ReadBuf::new(chunk.as_mut());
+            match ready!(Pin::new(&mut link.reader).poll_read(cx, &mut chunk_buf)) {
+                Ok(()) if chunk_buf.filled().is_empty() => {
+                    if !this.lost() {
+                        return Poll::Ready(Ok(()));
+                    }
+                },
+                Ok(()) => {
+                    let filled = chunk_buf.filled();
+                    this.received += filled.iter().filter(|&&b| b == b'\n').count();
+                    this.incoming.extend_from_slice(filled);
+                },
+                Err(e) => {
+                    if !this.lost() {
+                        return Poll::Ready(Err(e));
+                    }
+                },
+            }
+        }
+    }
+}
+
+impl AsyncWrite for Resumable {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        if this.closed {
+            return Poll::Ready(Err(io::ErrorKind::BrokenPipe.into()));
+        }
+        this.line.extend_from_slice(buf);
+        while let Some(end) = this.line.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = this.line.drain(..=end).collect();
+            this.outgoing.extend_from_slice(&line);
+            this.sent.push(Zeroizing::new(line));
+        }
+        // Send eagerly, like a socket would, so a role that never flushes is still heard
+        if let Poll::Ready(Err(e)) = this.poll_send(cx) {
+            return Poll::Ready(Err(e));
+        }
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.get_mut().poll_send(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_send(cx))?;
+        match this.link.as_mut() {
+            Some(link) => Pin::new(&mut link.writer).poll_shutdown(cx),
+            None => Poll::Ready(Ok(())),
+        }
+    }
+}
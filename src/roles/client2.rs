@@ -0,0 +1,249 @@
+//! Client2: announces the operation, joins the server's session and runs
+//! its half of the protocol, exchanging masked values with client1 over
+//! the link `peer` opens.
+
+use tracing::{debug, info, info_span, Instrument};
+use uuid::Uuid;
+use zeroize::Zeroizing;
+
+use crate::channel::Channel;
+use crate::cli;
+use crate::auth::Identity;
+use crate::commitment;
+use crate::du_atallah;
+use crate::error::ProtocolError;
+use crate::field::Field;
+use crate::input::Input;
+use crate::logging::secret;
+use crate::operation::Operation;
+use crate::randomness::RandomSource;
+use crate::relay;
+use crate::rendezvous::{self, Pairing};
+use crate::report::Report;
+use crate::roles::malicious;
+use crate::roles::state::{ClientMachine, Message, Values};
+use crate::poly;
+use crate::secret::Secret;
+use crate::share_file::{Scheme, ShareFile};
+use crate::stats::{self, MomentShares};
+use crate::transport::Link;
+use crate::wire;
+
+/// Run one session as client2, returning our output share if the operation
+/// leaves one. If it fails, the server and client1 are told to abort.
+/// `address` is registered with the server as our peer socket, and `peer`
+/// opens the link to client1 the way the server pairs us, see [`rendezvous`].
+pub async fn run(
+    op: Operation,
+    server: &mut Link,
+    input: &mut Input,
+    rng: &mut dyn RandomSource,
+    report: &mut Report,
+    address: &str,
+    peer: impl AsyncFnOnce(&mut dyn RandomSource, Pairing) -> anyhow::Result<Link>,
+) -> anyhow::Result<Option<ShareFile>> {
+    let mut server = Channel::new(server, "Server");
+    let mut p2p = None;
+
+    let result = async {
+        server.send(&op).await?;
+
+        let session: Uuid = server.recv().await?;
+        report.joined(session, op);
+        server.joined(session);
+
+        let span = info_span!("session", id = %session, role = "client2", %op);
+        async {
+            info!("Joined session {}", session);
+
+            info!("Linking with Client1...");
+            let pairing = rendezvous::pair(&mut server, address).await?;
+            let mut client1 = Channel::new(p2p.insert(peer(rng, pairing).await?), "Client1");
+            client1.joined(session);
+
+            match op {
+                Operation::Multiply => multiply(&mut server, input, session, rng, &mut client1).await,
+                Operation::PolyEval => poly_eval(&mut server, input, session, rng, &mut client1).await,
+                Operation::Stats => statistics(&mut server, input, session, rng, &mut client1).await,
+                Operation::MaliciousMultiply => malicious::malicious_multiply(2, &mut server, input, session, rng, &mut client1).await,
+                Operation::MultiplyN | Operation::ShamirMultiply => Err(ProtocolError::InvalidInput(format!("Operation {} is run with the party binary", op)).into()),
+            }
+        }
+        .instrument(span)
+        .await
+    }
+    .await;
+
+    // Tell everyone we are still connected to, so they stop now instead of timing out
+    if let Err(e) = &result {
+        server.abort("client2", e).await;
+        if let Some(p2p) = &mut p2p {
+            Channel::new(p2p, "Client1").abort("client2", e).await;
+        }
+    }
+    result
+}
+
+async fn multiply(
+    server: &mut Channel<'_>,
+    input: &mut Input,
+    session: Uuid,
+    rng: &mut dyn RandomSource,
+    p2p: &mut Channel<'_>,
+) -> anyhow::Result<Option<ShareFile>> {
+    // Get user input and create Secret struct
+    cli::prompt("Enter x1 (natural number):");
+    let x1_str = Zeroizing::new(wire::read_line(input, "input").await?);
+    
+    cli::prompt("Enter y1 (natural number):");
+    let y1_str = Zeroizing::new(wire::read_line(input, "input").await?);
+
+ 
+    let client2_secret = Secret::from_strings(&x1_str, &y1_str)?;
+    debug!(secret = ?client2_secret, "Created secret struct");
+
+    // Reduce into the protocol's field
+    let (x1, y1) = client2_secret.reduce(&Field::default());
+
+    // Send shares to server
+    info!(x1 = %secret(x1), y1 = %secret(y1), "Sending shares to server");
+    // Sign what we forward to Client1 with a key made for this session
+    let relay_key = Identity::generate("client2", rng);
+    server.send(&Message::Shares(Values { x: x1, y: y1, auth: hex::encode(relay_key.public_key().to_bytes()) })).await?;
+    let mut machine = ClientMachine::new("client2");
+
+    // Receive own masked values from server
+    info!("Waiting for masked values from server...");
+    let message = server.recv().await?;
+    let Values { x: mx1, y: my1, auth: peer_key } = machine.receive(server.peer(), message)?;
+    
+    debug!(mx1 = %secret(mx1), my1 = %secret(my1), "Received masked values");
+    let peer_key = relay::parse_key(server.peer(), &peer_key)?;
+
+    // Commit to our signed masked values before either of us reveals them
+    let signature = relay::sign(&relay_key, session, &[mx1, my1]);
+    info!("Exchanging commitments with Client1...");
+    p2p.send(&Message::Commit(relay::commit(session, "client2", &[mx1, my1], &signature))).await?;
+    machine.committed();
+    let message = p2p.recv().await?;
+    let Values { auth: peer_commitment, .. } = machine.receive(p2p.peer(), message)?;
+
+    // Send own masked values to Client1
+    info!("Sending masked values to Client1...");
+    p2p.send(&Message::Masked(Values { x: mx1, y: my1, auth: signature })).await?;
+    machine.masks_sent();
+
+    // Receive Client1's masked values
+    info!("Receiving masked values from Client1...");
+    let message = p2p.recv().await?;
+    let Values { x: mx0, y: my0, auth: signature } = machine.receive(p2p.peer(), message)?;
+    relay::open(&peer_commitment, session, "client1", &[mx0, my0], &signature)?;
+    if !relay::verify(&peer_key, session, "client1", &[mx0, my0], &signature) {
+        return Err(ProtocolError::Aborted("Client1 sent masked values with an invalid signature, session aborted".into()).into());
+    }
+    
+    debug!(mx0 = %secret(mx0), my0 = %secret(my0), "Received Client1's masked values");
+
+    info!("Sending exchanged values back to server...");
+    server.send(&Message::Relay(Values { x: mx0, y: my0, auth: signature })).await?;
+    machine.done();
+    
+    info!("Sent exchanged values to server");
+    info!("Done - Server will compute final result using Du-Atallah protocol");
+
+    Ok(None)
+}
+
+/// Evaluate client1's polynomial at our point, ending with an additive share of `P(x)`.
+async fn poly_eval(
+    server: &mut Channel<'_>,
+    input: &mut Input,
+    session: Uuid,
+    rng: &mut dyn RandomSource,
+    p2p: &mut Channel<'_>,
+) -> anyhow::Result<Option<ShareFile>> {
+    let field = Field::default();
+
+    info!("Waiting for polynomial size from server...");
+    let count: usize = server.recv().await?;
+    if count == 0 {
+        return Err(ProtocolError::Malformed("Server sent an empty polynomial".into()).into());
+    }
+    let correlations = server.recv_correlations(count).await?;
+
+    cli::prompt(&format!("Enter x (natural number) to evaluate a degree {} polynomial at:", count - 1));
+    let x = wire::read_value(input, "input").await?;
+    let powers = poly::powers(&field, x, count);
+
+    info!("Exchanging masked powers for Client1's masked coefficients...");
+    let masked_powers = du_atallah::mask(&field, &powers, &correlations);
+    let masked_coefficients = commitment::exchange(p2p, session, "Client2", &masked_powers, rng).await?;
+    if masked_coefficients.len() != count {
+        return Err(ProtocolError::Malformed(format!("Client1 sent {} coefficients, expected {}", masked_coefficients.len(), count)).into());
+    }
+
+    let share = du_atallah::client2_share(&field, &masked_coefficients, &correlations);
+    info!(share = %secret(share), "Computed share of P(x)");
+    let share_file = ShareFile {
+        session,
+        operation: Operation::PolyEval,
+        modulus: field.modulus(),
+        scheme: Scheme::Additive,
+        parties: 2,
+        party: 2,
+        values: vec![share],
+    };
+
+    server.send(&share).await?;
+    info!("Done - Server will open P(x) from both shares");
+
+    Ok(Some(share_file))
+}
+
+/// Contribute our half of the shared dataset to the sum and sum of squares.
+async fn statistics(
+    server: &mut Channel<'_>,
+    input: &mut Input,
+    session: Uuid,
+    rng: &mut dyn RandomSource,
+    p2p: &mut Channel<'_>,
+) -> anyhow::Result<Option<ShareFile>> {
+    let field = stats::STATS_FIELD;
+
+    cli::prompt("Enter your shares of the dataset (natural numbers, space separated):");
+    let values: Vec<u64> = wire::read_values(input, "input")
+        .await?
+        .into_iter()
+        .map(|v| field.reduce(v))
+        .collect();
+
+    info!("Sending record count {} to server", values.len());
+    server.send(&values.len()).await?;
+
+    info!("Waiting for correlated randomness from server...");
+    let correlations = server.recv_correlations(values.len()).await?;
+
+    info!("Exchanging masked shares with Client1...");
+    let masked_values = du_atallah::mask(&field, &values, &correlations);
+    let peer_masked = commitment::exchange(p2p, session, "Client2", &masked_values, rng).await?;
+    if peer_masked.len() != values.len() {
+        return Err(ProtocolError::Malformed(format!("Client1 sent {} masked shares, expected {}", peer_masked.len(), values.len())).into());
+    }
+
+    let cross = du_atallah::client2_share(&field, &peer_masked, &correlations);
+    let moments = MomentShares::new(&field, &values, cross);
+    let share_file = ShareFile {
+        session,
+        operation: Operation::Stats,
+        modulus: field.modulus(),
+        scheme: Scheme::Additive,
+        parties: 2,
+        party: 2,
+        values: vec![moments.sum, moments.sum_of_squares],
+    };
+
+    server.send_values(&[moments.sum, moments.sum_of_squares]).await?;
+    info!("Done - Server will open the statistics");
+
+    Ok(Some(share_file))
+}
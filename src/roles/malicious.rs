@@ -0,0 +1,109 @@
+//! Client side of `mmul`, the same for both clients apart from their index.
+//!
+//! Each client masks its inputs with the dealt masks and swaps the masked
+//! values with its peer, turning them into authenticated shares of `x` and
+//! `y`. The clients then open `d = x - a` and `e = y - b` to each other and
+//! compute authenticated shares of the product from the Beaver triple. What
+//! goes back to the server is blinded with a random zero-sharing picked by
+//! client1, so the server learns only the product and whether the MAC checks
+//! pass.
+
+use tracing::info;
+use uuid::Uuid;
+use zeroize::Zeroizing;
+
+use crate::channel::Channel;
+use crate::cli;
+use crate::commitment;
+use crate::error::ProtocolError;
+use crate::field::Field;
+use crate::input::Input;
+use crate::randomness::RandomSource;
+use crate::secret::Secret;
+use crate::share_file::ShareFile;
+use crate::spdz::{self, AuthShare, Preprocessing};
+use crate::wire;
+
+pub(crate) async fn malicious_multiply(
+    me: usize,
+    server: &mut Channel<'_>,
+    input: &mut Input,
+    session: Uuid,
+    rng: &mut dyn RandomSource,
+    p2p: &mut Channel<'_>,
+) -> anyhow::Result<Option<ShareFile>> {
+    let field = Field::default();
+    let name = format!("Client{}", me);
+
+    cli::prompt(&format!("Enter x{} (natural number):", me - 1));
+    let x = Zeroizing::new(wire::read_line(input, "input").await?);
+    cli::prompt(&format!("Enter y{} (natural number):", me - 1));
+    let y = Zeroizing::new(wire::read_line(input, "input").await?);
+    let secret = Secret::from_strings(&x, &y)?;
+    let (x, y) = secret.reduce(&field);
+
+    info!("Waiting for authenticated preprocessing from server...");
+    let pre = Preprocessing::from_values(&server.recv_values().await?)?;
+
+    // Input phase: publish our inputs masked with the masks only we know
+    info!("Exchanging masked inputs with {}...", p2p.peer());
+    let masked: Vec<u64> = [x, y].iter().zip(&pre.own_masks).map(|(&v, &r)| field.sub(v, r)).collect();
+    let peer_masked = swap(p2p, session, &name, &masked, rng).await?;
+    let all_masked = if me == 1 { [masked, peer_masked].concat() } else { [peer_masked, masked].concat() };
+    let inputs: Vec<AuthShare> = pre
+        .input_masks
+        .iter()
+        .zip(&all_masked)
+        .map(|(mask, &m)| mask.add_const(&field, me, pre.alpha_share, m))
+        .collect();
+    let x_share = inputs[0].add(&field, inputs[2]);
+    let y_share = inputs[1].add(&field, inputs[3]);
+
+    // Beaver multiplication: open d = x - a and e = y - b
+    info!("Opening the Beaver differences with {}...", p2p.peer());
+    let [a, b, _] = pre.triple;
+    let d_share = x_share.sub(&field, a);
+    let e_share = y_share.sub(&field, b);
+    let peer_de = swap(p2p, session, &name, &[d_share.value, e_share.value], rng).await?;
+    let d = field.add(d_share.value, peer_de[0]);
+    let e = field.add(e_share.value, peer_de[1]);
+    let product = spdz::beaver(&field, me, pre.alpha_share, &pre.triple, d, e);
+
+    // Zero-sharing that hides our individual report from the server
+    let blinding = if me == 1 {
+        let blinding = rng.field_elements(&field, spdz::REPORT_LEN);
+        p2p.send_values(&blinding).await?;
+        blinding
+    } else {
+        let blinding = p2p.recv_exactly(spdz::REPORT_LEN).await?;
+        blinding.into_iter().map(|v| field.neg(field.reduce(v))).collect()
+    };
+
+    let report = [
+        product.value,
+        product.mac,
+        d_share.check_term(&field, pre.alpha_share, d),
+        e_share.check_term(&field, pre.alpha_share, e),
+    ];
+    let report: Vec<u64> = report.iter().zip(&blinding).map(|(&v, &r)| field.add(v, r)).collect();
+    server.send_values(&report).await?;
+    info!("Done - Server will check the MACs and open the product");
+
+    Ok(None)
+}
+
+/// Swap `values` with the peer under commitments, expecting as many values back.
+async fn swap(
+    p2p: &mut Channel<'_>,
+    session: Uuid,
+    me: &str,
+    values: &[u64],
+    rng: &mut dyn RandomSource,
+) -> anyhow::Result<Vec<u64>> {
+    let field = Field::default();
+    let received = commitment::exchange(p2p, session, me, values, rng).await?;
+    if received.len() != values.len() {
+        return Err(ProtocolError::Malformed(format!("{} sent {} values, expected {}", p2p.peer(), received.len(), values.len())).into());
+    }
+    Ok(received.into_iter().map(|v| field.reduce(v)).collect())
+}
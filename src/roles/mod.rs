@@ -0,0 +1,10 @@
+//! Protocol logic for each role, independent of how its links were opened.
+//! The binaries connect these over Unix sockets; `simulate` runs them as tasks
+//! over in-memory links.
+
+pub mod client1;
+pub mod client2;
+mod malicious;
+pub mod party;
+pub mod server;
+pub mod state;
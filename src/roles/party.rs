@@ -0,0 +1,300 @@
+//! A party in `nmul` or `smul`: joins the server's n-party session, computes
+//! its share of the product with every other party over the links `peers`
+//! opens, and sends that share to the server.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use tracing::{info, info_span, warn, Instrument};
+use uuid::Uuid;
+
+use crate::channel::Channel;
+use crate::cli;
+use crate::commitment;
+use crate::du_atallah;
+use crate::error::{self, ProtocolError};
+use crate::field::Field;
+use crate::input::Input;
+use crate::nparty;
+use crate::operation::Operation;
+use crate::randomness::RandomSource;
+use crate::report::Report;
+use crate::shamir::{self, Share};
+use crate::share_file::{Scheme, ShareFile};
+use crate::transport::Link;
+use crate::wire;
+
+/// Run one session as party `me` of `parties`, sharing the product as
+/// `scheme` says, and return our share of it after sending it to the server.
+/// If it fails, the server and every linked peer are told to abort. `peers`
+/// opens the links to every other party, keyed by their index, once the
+/// protocol needs them.
+#[allow(clippy::too_many_arguments)]
+pub async fn run(
+    me: usize,
+    parties: usize,
+    scheme: Scheme,
+    server: &mut Link,
+    input: &mut Input,
+    rng: &mut dyn RandomSource,
+    report: &mut Report,
+    peers: impl AsyncFnOnce(&mut dyn RandomSource) -> anyhow::Result<BTreeMap<usize, Link>>,
+) -> anyhow::Result<ShareFile> {
+    let op = match scheme {
+        Scheme::Additive => Operation::MultiplyN,
+        Scheme::Shamir { .. } => Operation::ShamirMultiply,
+    };
+    let name = format!("Party{}", me);
+    let field = Field::default();
+    let mut server = Channel::new(server, "Server");
+    let mut links = BTreeMap::new();
+
+    let result = async {
+        server.send(&op).await?;
+        server.send(&parties).await?;
+        if let Scheme::Shamir { threshold } = scheme {
+            server.send(&threshold).await?;
+        }
+
+        let session: Uuid = server.recv().await?;
+        report.joined(session, op);
+        server.joined(session);
+        let span = info_span!("session", id = %session, role = %name, %op);
+        async {
+            info!("{}: Joined session {}", name, session);
+
+            cli::prompt(&format!("Enter x{} (natural number):", me));
+            let x = field.reduce(wire::read_value(input, "input").await?);
+            cli::prompt(&format!("Enter y{} (natural number):", me));
+            let y = field.reduce(wire::read_value(input, "input").await?);
+
+            let share = match scheme {
+                Scheme::Shamir { threshold } => {
+                    info!("{}: Drawing shares from {}", name, rng.name());
+                    shamir_share(&field, me, parties, threshold, x, y, session, rng, &mut server, &mut links, peers).await?
+                },
+                Scheme::Additive => additive_share(&field, me, parties, x, y, session, rng, &mut server, &mut links, peers).await?,
+            };
+
+            server.send(&share).await?;
+            info!("{}: Done - Server will open the product from all shares", name);
+
+            Ok(ShareFile {
+                session,
+                operation: op,
+                modulus: field.modulus(),
+                scheme,
+                parties,
+                party: me,
+                values: vec![share],
+            })
+        }
+        .instrument(span)
+        .await
+    }
+    .await;
+
+    // Tell everyone we are still connected to, so they stop now instead of timing out
+    if let Err(e) = &result {
+        server.abort(&report.role, e).await;
+        for (peer, link) in links.iter_mut() {
+            Channel::new(link, format!("Party{}", peer)).abort(&report.role, e).await;
+        }
+    }
+    result
+}
+
+/// Our additive share of the product: diagonal term plus one Du-Atallah
+/// cross-term product with every peer, using the server's correlations.
+#[allow(clippy::too_many_arguments)]
+async fn additive_share(
+    field: &Field,
+    me: usize,
+    parties: usize,
+    x: u64,
+    y: u64,
+    session: Uuid,
+    rng: &mut dyn RandomSource,
+    server: &mut Channel<'_>,
+    links: &mut BTreeMap<usize, Link>,
+    peers: impl AsyncFnOnce(&mut dyn RandomSource) -> anyhow::Result<BTreeMap<usize, Link>>,
+) -> anyhow::Result<u64> {
+    info!("Party{}: Waiting for correlated randomness from server...", me);
+    let mut correlations = BTreeMap::new();
+    for peer in (1..=parties).filter(|&p| p != me) {
+        let batch = server.recv_correlations(nparty::PRODUCTS_PER_PAIR).await?;
+        correlations.insert(peer, batch);
+    }
+
+    info!("Party{}: Linking with {} peers...", me, parties - 1);
+    *links = peers(rng).await?;
+
+    info!("Party{}: Exchanging masked cross-term inputs...", me);
+    let peer_masked = exchange(links, me, session, rng, 0, |peer| {
+        let masked = du_atallah::mask(field, &nparty::cross_vector(me, peer, x, y), &correlations[&peer]);
+        [masked[0], masked[1]]
+    })
+    .await?;
+
+    let cross_shares: Vec<u64> = peer_masked
+        .into_iter()
+        .map(|(peer, masked)| nparty::cross_share(field, me, peer, x, y, &masked, &correlations[&peer]))
+        .collect();
+
+    Ok(nparty::output_share(field, x, y, &cross_shares))
+}
+
+/// Our Shamir share of the product: share our inputs with every peer, add up
+/// the shares of `x` and `y`, multiply locally and reduce the degree together.
+#[allow(clippy::too_many_arguments)]
+async fn shamir_share(
+    field: &Field,
+    me: usize,
+    parties: usize,
+    threshold: usize,
+    x: u64,
+    y: u64,
+    session: Uuid,
+    rng: &mut dyn RandomSource,
+    server: &mut Channel<'_>,
+    links: &mut BTreeMap<usize, Link>,
+    peers: impl AsyncFnOnce(&mut dyn RandomSource) -> anyhow::Result<BTreeMap<usize, Link>>,
+) -> anyhow::Result<u64> {
+    // The server signals once every party is connected and listening
+    let ready: String = server.recv().await?;
+    if ready != "ready" {
+        return Err(ProtocolError::Malformed(format!("Unexpected message from server: {}", ready)).into());
+    }
+
+    info!("Party{}: Linking with {} peers...", me, parties - 1);
+    *links = peers(rng).await?;
+
+    info!("Party{}: Sharing inputs with threshold {}...", me, threshold);
+    let x_shares = shamir::share(field, rng, x, threshold, parties);
+    let y_shares = shamir::share(field, rng, y, threshold, parties);
+    let pieces = exchange(links, me, session, rng, 0, |peer| [x_shares[peer - 1].value, y_shares[peer - 1].value]).await?;
+
+    let mut x_share = x_shares[me - 1];
+    let mut y_share = y_shares[me - 1];
+    for (_, [px, py]) in pieces {
+        x_share = shamir::add(field, x_share, Share { index: me as u64, value: px });
+        y_share = shamir::add(field, y_share, Share { index: me as u64, value: py });
+    }
+
+    // Peers may drop out from here on, as long as enough reshared products arrive
+    info!("Party{}: Reducing the degree of the local product...", me);
+    let product = shamir::mul_local(field, x_share, y_share);
+    let reshares = shamir::reshare_product(field, rng, product, threshold, parties);
+    let may_miss = parties - shamir::reduction_quorum(threshold);
+    let mut received: Vec<(u64, u64)> = exchange(links, me, session, rng, may_miss, |peer| [reshares[peer - 1].value])
+        .await?
+        .into_iter()
+        .map(|(peer, [v])| (peer as u64, v))
+        .collect();
+    received.push((me as u64, reshares[me - 1].value));
+
+    // Every party must combine the same senders, so the server picks the ones all of us heard from
+    let senders: Vec<u64> = received.iter().map(|&(from, _)| from).collect();
+    server.send_values(&senders).await?;
+    let line: String = server.recv_within(shamir::reduction_deadline(threshold, parties)).await?;
+    let pieces = line
+        .split_whitespace()
+        .map(|v| {
+            let from: u64 = wire::parse(v, "Server")?;
+            received
+                .iter()
+                .find(|&&(sender, _)| sender == from)
+                .copied()
+                .ok_or_else(|| ProtocolError::Malformed(format!("Server picked Party{}, whose reshared product never reached Party{}", from, me)).into())
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    if pieces.len() < shamir::reduction_quorum(threshold) {
+        return Err(ProtocolError::Malformed(format!("Server picked {} senders, degree reduction needs {}", pieces.len(), shamir::reduction_quorum(threshold))).into());
+    }
+
+    Ok(shamir::reduce_degree(field, me as u64, &pieces)?.value)
+}
+
+/// Send each peer the values `outgoing(peer)` and collect what every peer sent
+/// us. Everyone commits to what they send before anyone reveals. Up to
+/// `may_miss` peers may go away on the way; they are left out of the result
+/// and their links are dropped.
+async fn exchange<const N: usize>(
+    links: &mut BTreeMap<usize, Link>,
+    me: usize,
+    session: Uuid,
+    rng: &mut dyn RandomSource,
+    may_miss: usize,
+    outgoing: impl Fn(usize) -> [u64; N],
+) -> anyhow::Result<Vec<(usize, [u64; N])>> {
+    let name = format!("Party{}", me);
+    let mut peers: Vec<(usize, Channel)> = links.iter_mut().map(|(&peer, link)| (peer, Channel::new(link, format!("Party{}", peer)))).collect();
+    let mut missing = BTreeSet::new();
+    let mut openings = BTreeMap::new();
+    for (peer, channel) in peers.iter_mut() {
+        let (commitment, opening) = commitment::commit(session, &name, &outgoing(*peer), rng);
+        if let Err(e) = channel.send(&commitment).await {
+            drop_out(&mut missing, may_miss, *peer, e)?;
+        }
+        openings.insert(*peer, opening);
+    }
+
+    let mut commitments = BTreeMap::new();
+    for (peer, channel) in peers.iter_mut() {
+        if missing.contains(peer) {
+            continue;
+        }
+        match channel.recv::<String>().await {
+            Ok(commitment) => {
+                commitments.insert(*peer, commitment);
+            },
+            Err(e) => drop_out(&mut missing, may_miss, *peer, e)?,
+        }
+    }
+
+    for (peer, channel) in peers.iter_mut() {
+        if missing.contains(peer) {
+            continue;
+        }
+        if let Err(e) = channel.send(&openings[peer].to_line()).await {
+            drop_out(&mut missing, may_miss, *peer, e)?;
+        }
+    }
+
+    let mut incoming = Vec::with_capacity(peers.len());
+    for (peer, channel) in peers.iter_mut() {
+        if missing.contains(peer) {
+            continue;
+        }
+        let peer = *peer;
+        let reveal: String = match channel.recv().await {
+            Ok(reveal) => reveal,
+            Err(e) => {
+                drop_out(&mut missing, may_miss, peer, e)?;
+                continue;
+            },
+        };
+        let values = commitment::open(&commitments[&peer], session, channel.peer(), &reveal)?;
+        let values: [u64; N] = values
+            .try_into()
+            .map_err(|v: Vec<u64>| ProtocolError::Malformed(format!("Party{} sent {} values to Party{}, expected {}", peer, v.len(), me, N)))?;
+        incoming.push((peer, values));
+    }
+    drop(peers);
+    links.retain(|peer, _| !missing.contains(peer));
+    Ok(incoming)
+}
+
+/// Leave out `peer`, which failed with `error`, unless it is one peer too many
+/// or the error says it misbehaved rather than went away.
+fn drop_out(missing: &mut BTreeSet<usize>, may_miss: usize, peer: usize, error: anyhow::Error) -> anyhow::Result<()> {
+    let misbehaved = matches!(
+        error::protocol_error(&error),
+        Some(ProtocolError::Malformed(_) | ProtocolError::Aborted(_) | ProtocolError::AuthFailed(_) | ProtocolError::InvalidInput(_))
+    );
+    if misbehaved || missing.len() >= may_miss {
+        return Err(error);
+    }
+    warn!("Party{} dropped out: {:#}", peer, error);
+    missing.insert(peer);
+    Ok(())
+}
@@ -0,0 +1,410 @@
+//! The server: agrees the operation with every client, hands out the session
+//! ID and masks or correlated randomness, and opens the result.
+
+use std::fmt;
+
+use tracing::{debug, info, info_span, warn, Instrument};
+use uuid::Uuid;
+
+use crate::channel::Channel;
+use crate::du_atallah::{self, Correlation};
+use crate::error::ProtocolError;
+use crate::field::Field;
+use crate::logging::secret;
+use crate::nparty;
+use crate::operation::Operation;
+use crate::randomness::RandomSource;
+use crate::relay;
+use crate::rendezvous::{self, Topology};
+use crate::report::Report;
+use crate::roles::state::{Message, ServerMachine, Values};
+use crate::shamir::{self, Share};
+use crate::spdz;
+use crate::stats::{self, MomentShares, Statistics};
+use crate::transport::Link;
+use crate::wire;
+
+/// What the server opened at the end of a session.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Outcome {
+    Value(u64),
+    Statistics(Statistics),
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Outcome::Value(v) => write!(f, "{}", v),
+            Outcome::Statistics(s) => write!(f, "count={}, sum={}, mean={}, variance={}", s.count, s.sum, s.mean(), s.variance()),
+        }
+    }
+}
+
+/// Run one session with the clients on `links`, in client order, pairing two
+/// clients for `topology`. If it fails, every client is told to abort.
+pub async fn run(links: &mut [Link], topology: &Topology, rng: &mut dyn RandomSource, report: &mut Report) -> anyhow::Result<Outcome> {
+    let mut clients: Vec<Channel> = (1..).zip(links.iter_mut()).map(|(k, link)| Channel::new(link, format!("Client{}", k))).collect();
+    let result = open_session(&mut clients, topology, rng, report).await;
+    if let Err(e) = &result {
+        for client in &mut clients {
+            client.abort("server", e).await;
+        }
+    }
+    result
+}
+
+/// Agree on the operation with the clients, hand out a session ID and run it.
+async fn open_session(clients: &mut [Channel<'_>], topology: &Topology, rng: &mut dyn RandomSource, report: &mut Report) -> anyhow::Result<Outcome> {
+    // Every client announces the operation it was started with
+    let mut ops = Vec::with_capacity(clients.len());
+    for client in clients.iter_mut() {
+        ops.push(client.recv::<Operation>().await?);
+    }
+    let op = ops[0];
+    if let Some(k) = ops.iter().position(|&o| o != op) {
+        return Err(ProtocolError::InvalidInput(format!("Clients disagree on operation: client1={}, client{}={}", op, k + 1, ops[k])).into());
+    }
+    info!("Operation: {}", op);
+    info!("Randomness: {}", rng.name());
+
+    let session = rng.uuid();
+    info!("Session: {}", session);
+    report.joined(session, op);
+    for client in clients.iter_mut() {
+        client.joined(session);
+        client.send(&session).await?;
+    }
+
+    let span = info_span!("session", id = %session, role = "server", %op);
+    run_session(clients, op, session, topology, rng).instrument(span).await
+}
+
+/// Run the operation the clients agreed on once they have joined `session`.
+async fn run_session(clients: &mut [Channel<'_>], op: Operation, session: Uuid, topology: &Topology, rng: &mut dyn RandomSource) -> anyhow::Result<Outcome> {
+    let parties = clients.len();
+    match op {
+        Operation::MultiplyN => return multiply_n(clients, rng).await,
+        Operation::ShamirMultiply => return shamir_multiply(clients).await,
+        _ => {},
+    }
+
+    let [client1, client2] = clients else {
+        return Err(ProtocolError::InvalidInput(format!("Operation {} needs exactly two clients, got {}", op, parties)).into());
+    };
+    rendezvous::broker(client1, client2, session, topology).await?;
+    match op {
+        Operation::Multiply => multiply(client1, client2, session, rng).await,
+        Operation::PolyEval => poly_eval(client1, client2, rng).await,
+        Operation::Stats => statistics(client1, client2, rng).await,
+        Operation::MaliciousMultiply => malicious_multiply(client1, client2, rng).await,
+        Operation::MultiplyN | Operation::ShamirMultiply => unreachable!(),
+    }
+}
+
+async fn multiply(
+    client1: &mut Channel<'_>,
+    client2: &mut Channel<'_>,
+    session: Uuid,
+    rng: &mut dyn RandomSource,
+) -> anyhow::Result<Outcome> {
+    // Generate an independent uniform mask for every share
+    let field = Field::default();
+    let rx0 = rng.field_element(&field);
+    let ry0 = rng.field_element(&field);
+    let rx1 = rng.field_element(&field);
+    let ry1 = rng.field_element(&field);
+
+    debug!(rx0 = %secret(rx0), ry0 = %secret(ry0), rx1 = %secret(rx1), ry1 = %secret(ry1), "Generated masks");
+
+    let mut machine = ServerMachine::default();
+
+    info!("Reading shares from client1...");
+    let Values { x: x0, y: y0, auth: key1 } = machine.receive(1, client1.recv().await?)?;
+    let key1 = relay::parse_key(client1.peer(), &key1)?;
+
+    info!("Reading shares from client2...");
+    let Values { x: x1, y: y1, auth: key2 } = machine.receive(2, client2.recv().await?)?;
+    let key2 = relay::parse_key(client2.peer(), &key2)?;
+
+    debug!(x0 = %secret(x0), y0 = %secret(y0), x1 = %secret(x1), y1 = %secret(y1), "Received shares");
+
+    // Compute masked values
+    let masked_x0 = field.add(x0, rx0);
+    let masked_y0 = field.add(y0, ry0);
+    let masked_x1 = field.add(x1, rx1);
+    let masked_y1 = field.add(y1, ry1);
+
+    // Send masked values to respective clients
+    info!("Sending masked values to clients...");
+    debug!(masked_x0 = %secret(masked_x0), masked_y0 = %secret(masked_y0), "Sending to client1");
+    client1.send(&Message::Masks(Values { x: masked_x0, y: masked_y0, auth: hex::encode(key2.to_bytes()) })).await?;
+
+    debug!(masked_x1 = %secret(masked_x1), masked_y1 = %secret(masked_y1), "Sending to client2");
+    client2.send(&Message::Masks(Values { x: masked_x1, y: masked_y1, auth: hex::encode(key1.to_bytes()) })).await?;
+    machine.masks_sent();
+
+    // Wait for clients to exchange data and send back the exchanged masked values
+    info!("Waiting for exchanged masked values from client1...");
+    let Values { x: mx1_from_client1, y: my1_from_client1, auth: signature1 } = machine.receive(1, client1.recv().await?)?;
+
+    info!("Waiting for exchanged masked values from client2...");
+    let Values { x: mx0_from_client2, y: my0_from_client2, auth: signature2 } = machine.receive(2, client2.recv().await?)?;
+
+    // Each relay must carry its originator's signature and match what we sent
+    let relayed1 = [mx1_from_client1, my1_from_client1];
+    let relayed2 = [mx0_from_client2, my0_from_client2];
+    relay::check(session, "client2", "client1", &key2, &[masked_x1, masked_y1], &relayed1, &signature1)?;
+    relay::check(session, "client1", "client2", &key1, &[masked_x0, masked_y0], &relayed2, &signature2)?;
+    info!("Relay check passed");
+
+    // DU-ATALLAH MULTIPLICATION PROTOCOL IMPLEMENTATION
+    debug!("Running the Du-Atallah aggregation");
+
+    // Direct terms: x0*y0 and x1*y1 (server has direct access)
+    let term1 = field.mul(x0, y0);
+    let term4 = field.mul(x1, y1);
+
+    // Cross terms from the relayed masked values, with our masks taken off
+    let recovered_y1 = field.sub(my1_from_client1, ry1);
+    let recovered_y0 = field.sub(my0_from_client2, ry0);
+    let term2 = field.mul(x0, recovered_y1);
+    let term3 = field.mul(x1, recovered_y0);
+
+    let final_result = field.sum([term1, term2, term3, term4]);
+
+    info!("Du-Atallah result: {}", final_result);
+    machine.done();
+
+    Ok(Outcome::Value(final_result))
+}
+
+/// Deal correlated randomness for one Du-Atallah product per coefficient and
+/// open `P(x)` from the output shares the clients return.
+async fn poly_eval(
+    client1: &mut Channel<'_>,
+    client2: &mut Channel<'_>,
+    rng: &mut dyn RandomSource,
+) -> anyhow::Result<Outcome> {
+    let field = Field::default();
+
+    let count: usize = client1.recv().await?;
+    if count == 0 {
+        return Err(ProtocolError::Malformed("Client1 sent an empty polynomial".into()).into());
+    }
+    info!("Polynomial with {} coefficients", count);
+    client2.send(&count).await?;
+
+    let (corr1, corr2) = du_atallah::deal(&field, rng, count);
+
+    info!("Sending correlated randomness to clients...");
+    client1.send_correlations(&corr1).await?;
+    client2.send_correlations(&corr2).await?;
+
+    info!("Waiting for output shares...");
+    let share1: u64 = client1.recv().await?;
+    let share2: u64 = client2.recv().await?;
+
+    let result = field.add(share1, share2);
+    info!("Polynomial result: {}", result);
+
+    Ok(Outcome::Value(result))
+}
+
+/// Deal correlated randomness for the cross-term inner product and open the
+/// dataset's sum and sum of squares, printing only the derived statistics.
+async fn statistics(
+    client1: &mut Channel<'_>,
+    client2: &mut Channel<'_>,
+    rng: &mut dyn RandomSource,
+) -> anyhow::Result<Outcome> {
+    let field = stats::STATS_FIELD;
+
+    let count1: usize = client1.recv().await?;
+    let count2: usize = client2.recv().await?;
+    if count1 != count2 {
+        return Err(ProtocolError::Malformed(format!("Clients hold {} and {} shares, expected the same length", count1, count2)).into());
+    }
+    if count1 == 0 {
+        return Err(ProtocolError::Malformed("Clients sent an empty dataset".into()).into());
+    }
+    info!("Dataset with {} records", count1);
+
+    let (corr1, corr2) = du_atallah::deal(&field, rng, count1);
+
+    info!("Sending correlated randomness to clients...");
+    client1.send_correlations(&corr1).await?;
+    client2.send_correlations(&corr2).await?;
+
+    info!("Waiting for moment shares...");
+    let shares1 = client1.recv_values().await?;
+    let shares2 = client2.recv_values().await?;
+    let (share1, share2) = match (shares1.as_slice(), shares2.as_slice()) {
+        ([s1, q1], [s2, q2]) => (
+            MomentShares { sum: *s1, sum_of_squares: *q1 },
+            MomentShares { sum: *s2, sum_of_squares: *q2 },
+        ),
+        _ => return Err(ProtocolError::Malformed("Clients must each send a sum and a sum of squares".into()).into()),
+    };
+
+    let result = Statistics::open(&field, count1, share1, share2);
+    info!("Statistics: count={}, sum={}, mean={}, variance={}", result.count, result.sum, result.mean(), result.variance());
+
+    Ok(Outcome::Statistics(result))
+}
+
+/// Deal correlated randomness for every pair's cross terms and open the
+/// product from the output shares of all parties.
+async fn multiply_n(
+    clients: &mut [Channel<'_>],
+    rng: &mut dyn RandomSource,
+) -> anyhow::Result<Outcome> {
+    let field = Field::default();
+    let parties = clients.len();
+
+    for client in clients.iter_mut() {
+        let claimed: usize = client.recv().await?;
+        if claimed != parties {
+            return Err(ProtocolError::InvalidInput(format!("{} expects {} parties, server has {}", client.peer(), claimed, parties)).into());
+        }
+    }
+
+    // correlations[k - 1][peer - 1] is party k's half of its product with peer
+    let mut correlations: Vec<Vec<Vec<Correlation>>> = (0..parties).map(|_| (0..parties).map(|_| Vec::new()).collect()).collect();
+    for (i, j) in nparty::pairs(parties) {
+        let (ci, cj) = du_atallah::deal(&field, rng, nparty::PRODUCTS_PER_PAIR);
+        correlations[i - 1][j - 1] = ci;
+        correlations[j - 1][i - 1] = cj;
+    }
+
+    info!("Sending correlated randomness for {} pairs...", parties * (parties - 1) / 2);
+    for (k, client) in (1..=parties).zip(clients.iter_mut()) {
+        for peer in (1..=parties).filter(|&p| p != k) {
+            client.send_correlations(&correlations[k - 1][peer - 1]).await?;
+        }
+    }
+
+    info!("Waiting for output shares...");
+    let mut shares = Vec::with_capacity(parties);
+    for client in clients.iter_mut() {
+        shares.push(client.recv::<u64>().await?);
+    }
+
+    let result = field.sum(shares);
+    info!("{}-party product: {}", parties, result);
+
+    Ok(Outcome::Value(result))
+}
+
+/// Release the parties once all are connected, agree on whose reshared
+/// products they combine, and open the product from the Shamir shares of
+/// whichever parties are still online at the end.
+async fn shamir_multiply(clients: &mut [Channel<'_>]) -> anyhow::Result<Outcome> {
+    let field = Field::default();
+    let parties = clients.len();
+
+    let mut thresholds = Vec::with_capacity(parties);
+    for client in clients.iter_mut() {
+        let claimed: usize = client.recv().await?;
+        if claimed != parties {
+            return Err(ProtocolError::InvalidInput(format!("{} expects {} parties, server has {}", client.peer(), claimed, parties)).into());
+        }
+        thresholds.push(client.recv::<usize>().await?);
+    }
+    let threshold = thresholds[0];
+    if thresholds.iter().any(|&t| t != threshold) {
+        return Err(ProtocolError::InvalidInput(format!("Clients disagree on the threshold: {:?}", thresholds)).into());
+    }
+    shamir::check_params(threshold, parties)?;
+
+    for client in clients.iter_mut() {
+        client.send("ready").await?;
+    }
+
+    // Each party reports whose reshared products reached it; every party has
+    // to combine the same ones, so they all use the senders everyone heard from
+    info!("Waiting for degree reduction senders (threshold {} of {})...", threshold, parties);
+    let deadline = shamir::reduction_deadline(threshold, parties);
+    let mut online = vec![true; parties];
+    let mut senders: Vec<u64> = (1..=parties as u64).collect();
+    for (k, client) in clients.iter_mut().enumerate() {
+        match client.recv_within::<String>(deadline).await {
+            Ok(line) => {
+                let heard: Vec<u64> = line.split_whitespace().map(|v| wire::parse(v, client.peer())).collect::<anyhow::Result<_>>()?;
+                senders.retain(|s| heard.contains(s));
+            },
+            Err(e) => {
+                warn!("{} dropped out: {}", client.peer(), e);
+                online[k] = false;
+            },
+        }
+    }
+    if senders.len() < shamir::reduction_quorum(threshold) {
+        return Err(ProtocolError::Aborted(format!(
+            "Only {} parties' reshared products reached every party, degree reduction needs {}",
+            senders.len(),
+            shamir::reduction_quorum(threshold)
+        ))
+        .into());
+    }
+    info!("Reducing the degree with the products of parties {:?}", senders);
+    for (online, client) in online.iter_mut().zip(clients.iter_mut()).filter(|(online, _)| **online) {
+        if let Err(e) = client.send_values(&senders).await {
+            warn!("{} dropped out: {}", client.peer(), e);
+            *online = false;
+        }
+    }
+
+    info!("Waiting for Shamir output shares...");
+    let mut shares = Vec::with_capacity(parties);
+    for (k, client) in clients.iter_mut().enumerate().filter(|&(k, _)| online[k]) {
+        match client.recv().await {
+            Ok(value) => shares.push(Share { index: k as u64 + 1, value }),
+            Err(e) => warn!("{} dropped out: {}", client.peer(), e),
+        }
+    }
+    if shares.len() < threshold {
+        return Err(ProtocolError::Aborted(format!("Only {} shares arrived, need {} to reconstruct", shares.len(), threshold)).into());
+    }
+
+    let result = shamir::reconstruct(&field, &shares)?;
+    info!("{}-party Shamir product from {} shares: {}", parties, shares.len(), result);
+
+    Ok(Outcome::Value(result))
+}
+
+/// Deal the MAC key, input masks and Beaver triple for `mmul`, then open the
+/// product only if every MAC check passes.
+async fn malicious_multiply(
+    client1: &mut Channel<'_>,
+    client2: &mut Channel<'_>,
+    rng: &mut dyn RandomSource,
+) -> anyhow::Result<Outcome> {
+    let field = Field::default();
+    let (pre1, pre2, alpha) = spdz::deal(&field, rng);
+
+    info!("Sending authenticated preprocessing to clients...");
+    client1.send_values(&pre1.to_values()).await?;
+    client2.send_values(&pre2.to_values()).await?;
+
+    // Each client reports its blinded output share, output MAC share and MAC
+    // check terms for the opened d and e; the blinding cancels in the sums
+    info!("Waiting for output shares and MAC check terms...");
+    let mut totals = [0u64; spdz::REPORT_LEN];
+    for report in [client1.recv_exactly(spdz::REPORT_LEN).await?, client2.recv_exactly(spdz::REPORT_LEN).await?] {
+        for (total, v) in totals.iter_mut().zip(report) {
+            *total = field.add(*total, field.reduce(v));
+        }
+    }
+    let [product, product_mac, d_check, e_check] = totals;
+
+    if d_check != 0 || e_check != 0 {
+        return Err(ProtocolError::Aborted("MAC check failed on the opened Beaver values: a client cheated, session aborted".into()).into());
+    }
+    if product_mac != field.mul(alpha, product) {
+        return Err(ProtocolError::Aborted("MAC check failed on the product: a client cheated, session aborted".into()).into());
+    }
+    info!("MAC check passed");
+    info!("Authenticated product: {}", product);
+
+    Ok(Outcome::Value(product))
+}
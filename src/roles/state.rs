@@ -0,0 +1,271 @@
+//! The `mul` exchange as explicit state machines, one per role.
+//!
+//! Every `mul` message is a single tagged line ([`Message`]). The roles do the
+//! I/O and hand each message they read to their machine, which accepts it only
+//! if it is the next one the protocol allows from that sender and otherwise
+//! rejects it as malformed. A rejected message moves the machine to
+//! [`State::Aborted`] for good, so a peer that repeats itself or skips ahead
+//! fails the session the same way every time. The machines do no I/O of their
+//! own and can be driven directly in tests.
+
+use std::fmt;
+use std::str::FromStr;
+
+use tracing::debug;
+
+use crate::error::ProtocolError;
+
+/// Where a role is in a `mul` session.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum State {
+    /// Waiting for the shares (server) or masked shares (clients) to arrive.
+    AwaitingPeers,
+    /// The server holds both clients' shares; a client holds its masked shares.
+    SharesReceived,
+    /// A client has sent the commitment to its masked shares and waits for its peer's.
+    Committed,
+    /// A client holds its peer's commitment and may reveal its own masked shares.
+    CommitmentsExchanged,
+    /// The masked values are on their way to the clients (server) or the peer (clients).
+    MasksSent,
+    /// The server holds both relays; a client holds its peer's masked values.
+    Exchanged,
+    /// The server opened the product; a client relayed its peer's values back.
+    Done,
+    /// A message arrived out of order, or the session failed.
+    Aborted,
+}
+
+/// Two values and the hex session key or signature that travels with them.
+///
+/// The values are shares of the clients' inputs and `auth` may be a session
+/// key, so `Debug` leaves all three out.
+#[derive(PartialEq, Eq)]
+pub struct Values {
+    pub x: u64,
+    pub y: u64,
+    pub auth: String,
+}
+
+/// One `mul` message, sent as `<tag> <x> <y> <auth>`, or `commit <digest>`.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Message {
+    /// Client to server: the client's shares and its session key.
+    Shares(Values),
+    /// Server to client: the client's masked shares and its peer's session key.
+    Masks(Values),
+    /// Client to client: the hex commitment to the `masked` message that follows.
+    Commit(String),
+    /// Client to client: the sender's masked shares, signed with its session key.
+    Masked(Values),
+    /// Client to server: the peer's masked shares passed back with the peer's signature.
+    Relay(Values),
+}
+
+impl fmt::Debug for Values {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Values")
+            .field("x", &"<redacted>")
+            .field("y", &"<redacted>")
+            .field("auth", &"<redacted>")
+            .finish()
+    }
+}
+
+impl Message {
+    fn tag(&self) -> &'static str {
+        match self {
+            Message::Shares(_) => "shares",
+            Message::Masks(_) => "masks",
+            Message::Commit(_) => "commit",
+            Message::Masked(_) => "masked",
+            Message::Relay(_) => "relay",
+        }
+    }
+
+    /// What the message carries; a commitment comes back as `auth`.
+    fn into_values(self) -> Values {
+        match self {
+            Message::Shares(v) | Message::Masks(v) | Message::Masked(v) | Message::Relay(v) => v,
+            Message::Commit(commitment) => Values { x: 0, y: 0, auth: commitment },
+        }
+    }
+}
+
+impl fmt::Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Message::Commit(commitment) => write!(f, "{} {}", self.tag(), commitment),
+            Message::Shares(v) | Message::Masks(v) | Message::Masked(v) | Message::Relay(v) => {
+                write!(f, "{} {} {} {}", self.tag(), v.x, v.y, v.auth)
+            },
+        }
+    }
+}
+
+impl FromStr for Message {
+    type Err = String;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        if let Some(commitment) = line.strip_prefix("commit ") {
+            return match commitment {
+                "" => Err("expected `commit <digest>`".into()),
+                _ if commitment.contains(' ') => Err("expected `commit <digest>`".into()),
+                _ => Ok(Message::Commit(commitment.to_string())),
+            };
+        }
+        let [tag, x, y, auth] = line.split(' ').collect::<Vec<_>>()[..] else {
+            return Err("expected `<tag> <x> <y> <auth>`".into());
+        };
+        let values = Values {
+            x: x.parse().map_err(|e| format!("x: {}", e))?,
+            y: y.parse().map_err(|e| format!("y: {}", e))?,
+            auth: auth.to_string(),
+        };
+        match tag {
+            "shares" => Ok(Message::Shares(values)),
+            "masks" => Ok(Message::Masks(values)),
+            "masked" => Ok(Message::Masked(values)),
+            "relay" => Ok(Message::Relay(values)),
+            other => Err(format!("unknown message `{}`", other)),
+        }
+    }
+}
+
+/// Move `state` to `next`, logging the transition.
+fn enter(role: &str, state: &mut State, next: State) {
+    debug!("{}: {:?} -> {:?}", role, state, next);
+    *state = next;
+}
+
+/// Reject `message` from `from`, aborting the machine.
+fn reject(role: &str, state: &mut State, from: &str, message: &Message, why: &str) -> ProtocolError {
+    let error = ProtocolError::Malformed(format!("{} sent {} {} ({} was {:?})", from, message.tag(), why, role, state));
+    enter(role, state, State::Aborted);
+    error
+}
+
+/// The server's side of `mul`, for clients 1 and 2.
+#[derive(Debug)]
+pub struct ServerMachine {
+    state: State,
+    shares: [bool; 2],
+    relays: [bool; 2],
+}
+
+impl Default for ServerMachine {
+    fn default() -> Self {
+        ServerMachine { state: State::AwaitingPeers, shares: [false; 2], relays: [false; 2] }
+    }
+}
+
+impl ServerMachine {
+    pub fn state(&self) -> State {
+        self.state
+    }
+
+    /// Accept `message` from client `k`, returning what it carries.
+    pub fn receive(&mut self, k: usize, message: Message) -> Result<Values, ProtocolError> {
+        let from = format!("Client{}", k);
+        let seen = match (&message, self.state) {
+            (Message::Shares(_), State::AwaitingPeers) => &mut self.shares,
+            (Message::Relay(_), State::MasksSent) => &mut self.relays,
+            (Message::Shares(_) | Message::Relay(_), State::Aborted | State::Done) => {
+                return Err(reject("server", &mut self.state, &from, &message, "after the session ended"));
+            },
+            (Message::Shares(_) | Message::Relay(_), _) => {
+                return Err(reject("server", &mut self.state, &from, &message, "out of order"));
+            },
+            _ => return Err(reject("server", &mut self.state, &from, &message, "to the server")),
+        };
+        let Some(seen) = seen.get_mut(k.wrapping_sub(1)) else {
+            return Err(reject("server", &mut self.state, &from, &message, "but is not a client of this session"));
+        };
+        if std::mem::replace(seen, true) {
+            return Err(reject("server", &mut self.state, &from, &message, "twice"));
+        }
+        match self.state {
+            State::AwaitingPeers if self.shares == [true; 2] => enter("server", &mut self.state, State::SharesReceived),
+            State::MasksSent if self.relays == [true; 2] => enter("server", &mut self.state, State::Exchanged),
+            _ => {},
+        }
+        Ok(message.into_values())
+    }
+
+    /// Both clients have been sent their masked shares.
+    pub fn masks_sent(&mut self) {
+        assert_eq!(self.state, State::SharesReceived, "masks are sent once both shares are in");
+        enter("server", &mut self.state, State::MasksSent);
+    }
+
+    /// The product has been opened.
+    pub fn done(&mut self) {
+        assert_eq!(self.state, State::Exchanged, "the product is opened once both relays are in");
+        enter("server", &mut self.state, State::Done);
+    }
+
+    /// The session failed for a reason of its own.
+    pub fn abort(&mut self) {
+        enter("server", &mut self.state, State::Aborted);
+    }
+}
+
+/// A client's side of `mul`, talking to the server and its peer.
+#[derive(Debug)]
+pub struct ClientMachine {
+    role: String,
+    state: State,
+}
+
+impl ClientMachine {
+    /// The machine for `role`, which has just sent its shares to the server.
+    pub fn new(role: &str) -> Self {
+        ClientMachine { role: role.to_string(), state: State::AwaitingPeers }
+    }
+
+    pub fn state(&self) -> State {
+        self.state
+    }
+
+    /// Accept `message` from `from`, returning what it carries. The peer's
+    /// masked shares are accepted only once both commitments are exchanged.
+    pub fn receive(&mut self, from: &str, message: Message) -> Result<Values, ProtocolError> {
+        let next = match (&message, self.state) {
+            (Message::Masks(_), State::AwaitingPeers) => State::SharesReceived,
+            (Message::Commit(_), State::Committed) => State::CommitmentsExchanged,
+            (Message::Masked(_), State::MasksSent) => State::Exchanged,
+            (Message::Masks(_) | Message::Commit(_) | Message::Masked(_), State::Aborted | State::Done) => {
+                return Err(reject(&self.role, &mut self.state, from, &message, "after the session ended"));
+            },
+            (Message::Masks(_) | Message::Commit(_) | Message::Masked(_), _) => {
+                return Err(reject(&self.role, &mut self.state, from, &message, "out of order"));
+            },
+            _ => return Err(reject(&self.role, &mut self.state, from, &message, "to a client")),
+        };
+        enter(&self.role, &mut self.state, next);
+        Ok(message.into_values())
+    }
+
+    /// Our commitment to our masked shares has been sent to the peer.
+    pub fn committed(&mut self) {
+        assert_eq!(self.state, State::SharesReceived, "the commitment is sent once the masked shares arrive");
+        enter(&self.role, &mut self.state, State::Committed);
+    }
+
+    /// Our masked shares have been revealed to the peer.
+    pub fn masks_sent(&mut self) {
+        assert_eq!(self.state, State::CommitmentsExchanged, "masked shares are revealed once both commitments are in");
+        enter(&self.role, &mut self.state, State::MasksSent);
+    }
+
+    /// The peer's masked shares have been relayed back to the server.
+    pub fn done(&mut self) {
+        assert_eq!(self.state, State::Exchanged, "the relay is sent once the peer's values are in");
+        enter(&self.role, &mut self.state, State::Done);
+    }
+
+    /// The session failed for a reason of its own.
+    pub fn abort(&mut self) {
+        enter(&self.role, &mut self.state, State::Aborted);
+    }
+}
@@ -0,0 +1,62 @@
+//! A client's pair of inputs for the two-party multiplication.
+//!
+//! The inputs are kept as the decimal digits they were entered as, so values of
+//! any size fit, and are only ever reduced into the protocol's field. The
+//! digits are wiped when the secret is dropped, never appear in `Debug` or
+//! `Display` output, and the type cannot be cloned.
+
+use std::fmt;
+
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+use crate::error::ProtocolError;
+use crate::field::Field;
+
+#[derive(Zeroize, ZeroizeOnDrop)]
+pub struct Secret {
+    x: String,
+    y: String,
+}
+
+impl Secret {
+    pub fn new(x: u64, y: u64) -> Self {
+        Secret {
+            x: x.to_string(),
+            y: y.to_string(),
+        }
+    }
+
+    pub fn from_strings(x_str: &str, y_str: &str) -> anyhow::Result<Self> {
+        for (name, digits) in [("x", x_str), ("y", y_str)] {
+            if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+                return Err(ProtocolError::InvalidInput(format!("{} is not a natural number", name)).into());
+            }
+        }
+        Ok(Secret {
+            x: x_str.to_string(),
+            y: y_str.to_string(),
+        })
+    }
+
+    /// Both values reduced into `field`, so inputs of any size fit the protocol.
+    pub fn reduce(&self, field: &Field) -> (u64, u64) {
+        let reduce = |digits: &str| {
+            digits
+                .bytes()
+                .fold(0, |acc, b| field.add(field.mul(acc, 10), u64::from(b - b'0')))
+        };
+        (reduce(&self.x), reduce(&self.y))
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Secret { x: <redacted>, y: <redacted> }")
+    }
+}
+
+impl fmt::Display for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("<redacted>")
+    }
+}
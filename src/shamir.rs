@@ -0,0 +1,139 @@
+//! Shamir `(t, n)` secret sharing over the prime field. A secret is the
+//! constant term of a random polynomial of degree `t - 1` and party `k` holds
+//! its value at `k`, so any `t` shares reconstruct it and fewer reveal nothing.
+//!
+//! Shares add locally. Multiplying two shares locally gives a point on a
+//! degree `2(t - 1)` polynomial; each party reshares that product with degree
+//! `t - 1` and combines the pieces it receives with Lagrange coefficients
+//! (Gennaro-Rabin-Rabin degree reduction). This needs the pieces of `2t - 1`
+//! senders, the same ones at every party, so `n >= 2t - 1`.
+//!
+//! Every input is part of the product, so all `n` parties must share their
+//! inputs. After that, up to `n - (2t - 1)` parties may drop out during degree
+//! reduction and up to `n - t` before the product is opened.
+
+use std::fmt;
+use std::time::Duration;
+
+use crate::error::ProtocolError;
+use crate::field::Field;
+use crate::randomness::RandomSource;
+use crate::wire::READ_TIMEOUT;
+
+/// Party `index`'s point on a sharing polynomial.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Share {
+    pub index: u64,
+    pub value: u64,
+}
+
+impl fmt::Debug for Share {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Share").field("index", &self.index).field("value", &"<redacted>").finish()
+    }
+}
+
+/// Check that `threshold` shares out of `parties` can both reconstruct and multiply.
+pub fn check_params(threshold: usize, parties: usize) -> anyhow::Result<()> {
+    if threshold == 0 {
+        return Err(ProtocolError::InvalidInput("Threshold must be at least 1".into()).into());
+    }
+    if parties < reduction_quorum(threshold) {
+        return Err(ProtocolError::InvalidInput(format!(
+            "Multiplication with threshold {} needs at least {} parties, got {}",
+            threshold,
+            reduction_quorum(threshold),
+            parties
+        ))
+        .into());
+    }
+    Ok(())
+}
+
+/// How many senders' reshared products degree reduction combines, at least.
+pub fn reduction_quorum(threshold: usize) -> usize {
+    2 * threshold - 1
+}
+
+/// How long to wait for the outcome of degree reduction, in which each of
+/// the parties that may drop out can cost one read timeout.
+pub fn reduction_deadline(threshold: usize, parties: usize) -> Duration {
+    READ_TIMEOUT * (parties + 1 - reduction_quorum(threshold)) as u32
+}
+
+/// Split `secret` into `parties` shares, any `threshold` of which reconstruct it.
+pub fn share<R: RandomSource + ?Sized>(field: &Field, rng: &mut R, secret: u64, threshold: usize, parties: usize) -> Vec<Share> {
+    let mut coefficients = Vec::with_capacity(threshold);
+    coefficients.push(field.reduce(secret));
+    coefficients.extend(rng.field_elements(field, threshold - 1));
+
+    (1..=parties as u64)
+        .map(|index| Share {
+            index,
+            value: coefficients
+                .iter()
+                .rev()
+                .fold(0, |acc, &c| field.add(field.mul(acc, index), c)),
+        })
+        .collect()
+}
+
+/// Lagrange coefficients for interpolating at zero from the points `indices`.
+pub fn lagrange_at_zero(field: &Field, indices: &[u64]) -> anyhow::Result<Vec<u64>> {
+    indices
+        .iter()
+        .map(|&i| {
+            let mut numerator = field.reduce(1);
+            let mut denominator = field.reduce(1);
+            for &j in indices.iter().filter(|&&j| j != i) {
+                numerator = field.mul(numerator, j);
+                denominator = field.mul(denominator, field.sub(j, i));
+            }
+            let inverse = field
+                .inv(denominator)
+                .ok_or_else(|| ProtocolError::InvalidInput(format!("Duplicate or zero share index {}", i)))?;
+            Ok(field.mul(numerator, inverse))
+        })
+        .collect()
+}
+
+/// Recover the secret from shares of a polynomial whose degree is below `shares.len()`.
+pub fn reconstruct(field: &Field, shares: &[Share]) -> anyhow::Result<u64> {
+    if shares.is_empty() {
+        return Err(ProtocolError::InvalidInput("No shares to reconstruct from".into()).into());
+    }
+    let indices: Vec<u64> = shares.iter().map(|s| s.index).collect();
+    let coefficients = lagrange_at_zero(field, &indices)?;
+    Ok(field.sum(shares.iter().zip(coefficients).map(|(s, c)| field.mul(s.value, c))))
+}
+
+/// Share of `a + b` from shares of `a` and `b` held by the same party.
+pub fn add(field: &Field, a: Share, b: Share) -> Share {
+    debug_assert_eq!(a.index, b.index);
+    Share { index: a.index, value: field.add(a.value, b.value) }
+}
+
+/// Local product of two shares. The result lies on a polynomial of twice the
+/// degree and must be passed through `reshare_product` and `reduce_degree`.
+pub fn mul_local(field: &Field, a: Share, b: Share) -> Share {
+    debug_assert_eq!(a.index, b.index);
+    Share { index: a.index, value: field.mul(a.value, b.value) }
+}
+
+/// Reshare a local product with degree `threshold - 1`, one piece per party.
+pub fn reshare_product<R: RandomSource + ?Sized>(field: &Field, rng: &mut R, product: Share, threshold: usize, parties: usize) -> Vec<Share> {
+    share(field, rng, product.value, threshold, parties)
+}
+
+/// Combine the pieces party `me` received from the parties' `reshare_product`
+/// into its degree `threshold - 1` share of the product. Each entry pairs the
+/// sender's index with the piece it sent. Every party must combine pieces from
+/// the same senders, at least [`reduction_quorum`] of them.
+pub fn reduce_degree(field: &Field, me: u64, pieces: &[(u64, u64)]) -> anyhow::Result<Share> {
+    let senders: Vec<u64> = pieces.iter().map(|&(from, _)| from).collect();
+    let coefficients = lagrange_at_zero(field, &senders)?;
+    Ok(Share {
+        index: me,
+        value: field.sum(pieces.iter().zip(coefficients).map(|(&(_, v), c)| field.mul(v, c))),
+    })
+}
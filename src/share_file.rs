@@ -0,0 +1,224 @@
+//! Output shares written to disk by each party so they can be combined later
+//! with `owner reconstruct`. A share file is a small `key=value` text file:
+//!
+//! ```text
+//! session=5f0c8e0a-3a8e-4c55-9a53-1b3f4ad2c6a1
+//! operation=poly
+//! modulus=1000000007
+//! scheme=additive
+//! parties=2
+//! party=1
+//! values=852479174
+//! ```
+//!
+//! Shamir files add a `threshold=T` line.
+
+use std::fmt::{self, Write as _};
+use std::fs;
+use std::path::Path;
+
+use anyhow::Context;
+use tracing::info;
+use uuid::Uuid;
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::additive;
+use crate::cli;
+use crate::error::ProtocolError;
+use crate::field::Field;
+use crate::operation::Operation;
+use crate::shamir::{self, Share};
+
+/// How the values in a share file were shared.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scheme {
+    Additive,
+    Shamir { threshold: usize },
+}
+
+/// One party's output shares and what they belong to. The values are wiped on
+/// drop and left out of `Debug` output.
+#[derive(PartialEq, Eq)]
+pub struct ShareFile {
+    pub session: Uuid,
+    pub operation: Operation,
+    pub modulus: u64,
+    pub scheme: Scheme,
+    pub parties: usize,
+    pub party: usize,
+    pub values: Vec<u64>,
+}
+
+impl fmt::Debug for ShareFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ShareFile")
+            .field("session", &self.session)
+            .field("operation", &self.operation)
+            .field("modulus", &self.modulus)
+            .field("scheme", &self.scheme)
+            .field("parties", &self.parties)
+            .field("party", &self.party)
+            .field("values", &"<redacted>")
+            .finish()
+    }
+}
+
+impl Drop for ShareFile {
+    fn drop(&mut self) {
+        self.values.zeroize();
+    }
+}
+
+impl ShareFile {
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "session={}", self.session);
+        let _ = writeln!(out, "operation={}", self.operation);
+        let _ = writeln!(out, "modulus={}", self.modulus);
+        match self.scheme {
+            Scheme::Additive => {
+                let _ = writeln!(out, "scheme=additive");
+            },
+            Scheme::Shamir { threshold } => {
+                let _ = writeln!(out, "scheme=shamir");
+                let _ = writeln!(out, "threshold={}", threshold);
+            },
+        }
+        let _ = writeln!(out, "parties={}", self.parties);
+        let _ = writeln!(out, "party={}", self.party);
+        let values: Zeroizing<Vec<String>> = Zeroizing::new(self.values.iter().map(u64::to_string).collect());
+        let _ = writeln!(out, "values={}", values.join(" "));
+        out
+    }
+
+    pub fn parse(text: &str) -> anyhow::Result<Self> {
+        let mut session = None;
+        let mut operation = None;
+        let mut modulus = None;
+        let mut scheme = None;
+        let mut threshold = None;
+        let mut parties = None;
+        let mut party = None;
+        let mut values = None;
+
+        for line in text.lines().map(str::trim).filter(|l| !l.is_empty()) {
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| ProtocolError::Malformed(format!("Malformed share file line: {}", line)))?;
+            let malformed = |e: &dyn std::fmt::Display| ProtocolError::Malformed(format!("Malformed share file {}: {}", key, e));
+            match key {
+                "session" => session = Some(value.parse::<Uuid>().map_err(|e| malformed(&e))?),
+                "operation" => operation = Some(value.parse::<Operation>().map_err(|e| malformed(&e))?),
+                "modulus" => modulus = Some(Field::try_new(value.parse::<u64>().map_err(|e| malformed(&e))?)?.modulus()),
+                "scheme" => scheme = Some(value.to_string()),
+                "threshold" => threshold = Some(value.parse::<usize>().map_err(|e| malformed(&e))?),
+                "parties" => parties = Some(value.parse::<usize>().map_err(|e| malformed(&e))?),
+                "party" => party = Some(value.parse::<usize>().map_err(|e| malformed(&e))?),
+                "values" => {
+                    values = Some(
+                        value
+                            .split_whitespace()
+                            .map(|v| v.parse::<u64>())
+                            .collect::<Result<Vec<_>, _>>()
+                            .map_err(|e| malformed(&e))?,
+                    )
+                },
+                other => return Err(ProtocolError::Malformed(format!("Unknown share file key: {}", other)).into()),
+            }
+        }
+
+        let missing = |key: &str| ProtocolError::Malformed(format!("Share file is missing {}", key));
+        let scheme = match scheme.as_deref() {
+            Some("additive") => Scheme::Additive,
+            Some("shamir") => Scheme::Shamir { threshold: threshold.ok_or_else(|| missing("threshold"))? },
+            Some(other) => return Err(ProtocolError::Malformed(format!("Unknown sharing scheme: {}", other)).into()),
+            None => return Err(missing("scheme").into()),
+        };
+
+        Ok(ShareFile {
+            session: session.ok_or_else(|| missing("session"))?,
+            operation: operation.ok_or_else(|| missing("operation"))?,
+            modulus: modulus.ok_or_else(|| missing("modulus"))?,
+            scheme,
+            parties: parties.ok_or_else(|| missing("parties"))?,
+            party: party.ok_or_else(|| missing("party"))?,
+            values: values.ok_or_else(|| missing("values"))?,
+        })
+    }
+
+    pub fn read(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)
+            .map_err(|e| ProtocolError::InvalidInput(format!("Cannot read {}: {}", path.display(), e)))?;
+        Self::parse(&text).with_context(|| path.display().to_string())
+    }
+
+    pub fn write(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        Ok(fs::write(path, self.to_text())?)
+    }
+
+    /// Write this share to the path given with `--share-file`, if any.
+    pub fn save_if_requested(&self) -> anyhow::Result<()> {
+        if let Some(path) = cli::flag("--share-file") {
+            self.write(&path)?;
+            info!("Wrote party {}'s output share to {}", self.party, path);
+        }
+        Ok(())
+    }
+}
+
+/// Check that `files` are shares of the same session in the same field and
+/// open the values they hide, elementwise.
+pub fn reconstruct(files: &[ShareFile]) -> anyhow::Result<Vec<u64>> {
+    let first = files.first().ok_or_else(|| ProtocolError::InvalidInput("No share files given".into()))?;
+
+    for f in files {
+        if f.session != first.session {
+            return Err(ProtocolError::InvalidInput(format!("Party{} belongs to session {}, expected {}", f.party, f.session, first.session)).into());
+        }
+        if f.modulus != first.modulus {
+            return Err(ProtocolError::InvalidInput(format!("Party{} uses modulus {}, expected {}", f.party, f.modulus, first.modulus)).into());
+        }
+        if f.operation != first.operation || f.scheme != first.scheme || f.parties != first.parties {
+            return Err(ProtocolError::InvalidInput(format!("Party{} disagrees on the operation, scheme or party count", f.party)).into());
+        }
+        if f.values.len() != first.values.len() {
+            return Err(ProtocolError::InvalidInput(format!("Party{} holds {} values, expected {}", f.party, f.values.len(), first.values.len())).into());
+        }
+        if f.party == 0 || f.party > first.parties {
+            return Err(ProtocolError::InvalidInput(format!("Party index {} is not in 1..={}", f.party, first.parties)).into());
+        }
+    }
+    let mut seen: Vec<usize> = files.iter().map(|f| f.party).collect();
+    seen.sort_unstable();
+    seen.dedup();
+    if seen.len() != files.len() {
+        return Err(ProtocolError::InvalidInput("The same party's share was given more than once".into()).into());
+    }
+
+    let field = Field::new(first.modulus);
+    match first.scheme {
+        Scheme::Additive => {
+            if files.len() != first.parties {
+                return Err(ProtocolError::InvalidInput(format!("Additive sharing needs all {} shares, got {}", first.parties, files.len())).into());
+            }
+            Ok((0..first.values.len())
+                .map(|i| additive::combine(&field, &files.iter().map(|f| f.values[i]).collect::<Vec<_>>()))
+                .collect())
+        },
+        Scheme::Shamir { threshold } => {
+            if files.len() < threshold {
+                return Err(ProtocolError::InvalidInput(format!("Shamir sharing needs {} shares, got {}", threshold, files.len())).into());
+            }
+            (0..first.values.len())
+                .map(|i| {
+                    let shares: Vec<Share> = files
+                        .iter()
+                        .map(|f| Share { index: f.party as u64, value: f.values[i] })
+                        .collect();
+                    shamir::reconstruct(&field, &shares)
+                })
+                .collect()
+        },
+    }
+}
@@ -0,0 +1,83 @@
+//! Run a whole two-client session inside one process. The server and both
+//! clients run concurrently over in-memory links, so a session needs no
+//! sockets and every line exchanged is captured in the transcript.
+
+use std::io::Cursor;
+
+use anyhow::Context;
+
+use crate::error::ProtocolError;
+use crate::input::Input;
+use crate::operation::Operation;
+use crate::randomness::RandomSource;
+use crate::rendezvous::Topology;
+use crate::roles::server::Outcome;
+use crate::report::Report;
+use crate::roles::{client1, client2, server};
+use crate::share_file::ShareFile;
+use crate::transport::{Link, Message, Transcript};
+
+/// Everything a simulated session produced.
+#[derive(Debug)]
+pub struct Simulation {
+    pub outcome: Outcome,
+    /// Output shares left with client1 and client2, for operations that keep them.
+    pub shares: [Option<ShareFile>; 2],
+    pub transcript: Vec<Message>,
+    /// The server's, client1's and client2's reports, in that order.
+    pub reports: [Report; 3],
+}
+
+/// Run `op` with `inputs[0]` and `inputs[1]` as what client1 and client2
+/// would read from their terminal. `rngs` are the server's, client1's and
+/// client2's randomness, in that order.
+pub async fn simulate(op: Operation, inputs: [&str; 2], rngs: [Box<dyn RandomSource>; 3]) -> anyhow::Result<Simulation> {
+    if op.is_multiparty() {
+        return Err(ProtocolError::InvalidInput(format!("Operation {} cannot be simulated with two clients", op)).into());
+    }
+
+    let transcript = Transcript::default();
+    let (server1, mut to_server1) = Link::in_memory("server", "client1", &transcript);
+    let (server2, mut to_server2) = Link::in_memory("server", "client2", &transcript);
+    let (to_client2, to_client1) = Link::in_memory("client1", "client2", &transcript);
+
+    let mut input1: Input = Box::new(Cursor::new(inputs[0].as_bytes().to_vec()));
+    let mut input2: Input = Box::new(Cursor::new(inputs[1].as_bytes().to_vec()));
+
+    // Each role owns its links, so a role that fails hangs up on its peers
+    // instead of leaving them waiting for the read timeout.
+    let [mut rng, mut rng1, mut rng2] = rngs;
+    let server = async move {
+        let mut links = [server1, server2];
+        let mut report = Report::new("server");
+        let outcome = server::run(&mut links, &Topology::Direct, rng.as_mut(), &mut report).await;
+        if let Ok(outcome) = &outcome {
+            report.outcome(outcome);
+        }
+        (outcome, report)
+    };
+    let client1 = async move {
+        let mut report = Report::new("client1");
+        let peer = async move |_: &mut dyn RandomSource, _| Ok(to_client2);
+        let share = client1::run(op, &mut to_server1, &mut input1, rng1.as_mut(), &mut report, "memory", peer).await;
+        (share, report)
+    };
+    let client2 = async move {
+        let mut report = Report::new("client2");
+        let peer = async move |_: &mut dyn RandomSource, _| Ok(to_client1);
+        let share = client2::run(op, &mut to_server2, &mut input2, rng2.as_mut(), &mut report, "memory", peer).await;
+        (share, report)
+    };
+    let ((outcome, report), (share1, report1), (share2, report2)) = tokio::join!(server, client1, client2);
+
+    let share1 = share1.context("client1")?;
+    let share2 = share2.context("client2")?;
+    let outcome = outcome.context("server")?;
+
+    Ok(Simulation {
+        outcome,
+        shares: [share1, share2],
+        transcript: transcript.messages(),
+        reports: [report, report1, report2],
+    })
+}
@@ -0,0 +1,167 @@
+//! SPDZ-style authenticated shares for the malicious-security multiply (`mmul`).
+//!
+//! The server acts as the trusted dealer. It draws a global MAC key `alpha`,
+//! gives each client an additive share of it, and authenticates every dealt
+//! value `v` with MAC shares summing to `alpha * v`. Clients combine
+//! authenticated shares linearly, which keeps the MACs valid, and multiply with
+//! a Beaver triple. Changing a share or an opened value without the matching
+//! change to its MAC means guessing `alpha`, which succeeds with probability
+//! `1/p`, so the server's final check catches cheating.
+
+use std::fmt;
+
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+use crate::error::ProtocolError;
+use crate::field::Field;
+use crate::randomness::RandomSource;
+
+/// Input values of an `mmul` session, in the order every message lists them:
+/// client1's `x0, y0`, then client2's `x1, y1`.
+pub const INPUTS: usize = 4;
+
+/// Inputs each client owns.
+pub const INPUTS_PER_CLIENT: usize = 2;
+
+/// Values each client reports to the server at the end: its output share, its
+/// output MAC share, and its MAC check terms for the opened `d` and `e`.
+pub const REPORT_LEN: usize = 4;
+
+/// One party's share of a value together with its share of the value's MAC.
+#[derive(Clone, Copy, PartialEq, Eq, Zeroize)]
+pub struct AuthShare {
+    pub value: u64,
+    pub mac: u64,
+}
+
+impl fmt::Debug for AuthShare {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("AuthShare { value: <redacted>, mac: <redacted> }")
+    }
+}
+
+impl AuthShare {
+    pub fn add(&self, field: &Field, other: AuthShare) -> AuthShare {
+        AuthShare { value: field.add(self.value, other.value), mac: field.add(self.mac, other.mac) }
+    }
+
+    pub fn sub(&self, field: &Field, other: AuthShare) -> AuthShare {
+        AuthShare { value: field.sub(self.value, other.value), mac: field.sub(self.mac, other.mac) }
+    }
+
+    pub fn scale(&self, field: &Field, k: u64) -> AuthShare {
+        AuthShare { value: field.mul(self.value, k), mac: field.mul(self.mac, k) }
+    }
+
+    /// Add the public constant `c`: client1 adds it to its share, and every
+    /// client adds `alpha_share * c` to its MAC share.
+    pub fn add_const(&self, field: &Field, me: usize, alpha_share: u64, c: u64) -> AuthShare {
+        AuthShare {
+            value: if me == 1 { field.add(self.value, c) } else { self.value },
+            mac: field.add(self.mac, field.mul(alpha_share, c)),
+        }
+    }
+
+    /// Our term of the MAC check for a value opened as `opened`. The terms of
+    /// all parties sum to zero exactly when the opened value is authentic.
+    pub fn check_term(&self, field: &Field, alpha_share: u64, opened: u64) -> u64 {
+        field.sub(self.mac, field.mul(alpha_share, opened))
+    }
+}
+
+/// Everything the dealer gives one client before an `mmul` session, wiped on drop.
+#[derive(PartialEq, Eq, Zeroize, ZeroizeOnDrop)]
+pub struct Preprocessing {
+    pub alpha_share: u64,
+    /// Authenticated shares of one random mask per input, in `INPUTS` order.
+    pub input_masks: Vec<AuthShare>,
+    /// The masks of this client's own inputs in the clear.
+    pub own_masks: Vec<u64>,
+    /// Authenticated shares of a Beaver triple `(a, b, a * b)`.
+    pub triple: [AuthShare; 3],
+}
+
+impl Preprocessing {
+    /// Flatten into one line of field elements for the wire.
+    pub fn to_values(&self) -> Vec<u64> {
+        let mut values = vec![self.alpha_share];
+        for share in self.input_masks.iter().chain(&self.triple) {
+            values.push(share.value);
+            values.push(share.mac);
+        }
+        values.extend(&self.own_masks);
+        values
+    }
+
+    pub fn from_values(values: &[u64]) -> anyhow::Result<Self> {
+        let expected = 1 + 2 * (INPUTS + 3) + INPUTS_PER_CLIENT;
+        if values.len() != expected {
+            return Err(ProtocolError::Malformed(format!("Preprocessing has {} values, expected {}", values.len(), expected)).into());
+        }
+        let shares: Vec<AuthShare> = values[1..1 + 2 * (INPUTS + 3)]
+            .chunks(2)
+            .map(|c| AuthShare { value: c[0], mac: c[1] })
+            .collect();
+        Ok(Preprocessing {
+            alpha_share: values[0],
+            input_masks: shares[..INPUTS].to_vec(),
+            own_masks: values[1 + 2 * (INPUTS + 3)..].to_vec(),
+            triple: [shares[INPUTS], shares[INPUTS + 1], shares[INPUTS + 2]],
+        })
+    }
+}
+
+/// Authenticated two-party sharing of `value` under `alpha`.
+fn authenticate<R: RandomSource + ?Sized>(field: &Field, rng: &mut R, alpha: u64, value: u64) -> (AuthShare, AuthShare) {
+    let value1 = rng.field_element(field);
+    let mac1 = rng.field_element(field);
+    (
+        AuthShare { value: value1, mac: mac1 },
+        AuthShare { value: field.sub(value, value1), mac: field.sub(field.mul(alpha, value), mac1) },
+    )
+}
+
+/// Deal a fresh MAC key, input masks and a Beaver triple. Returns client1's
+/// and client2's preprocessing and the MAC key the server keeps for the check.
+pub fn deal<R: RandomSource + ?Sized>(field: &Field, rng: &mut R) -> (Preprocessing, Preprocessing, u64) {
+    let alpha = rng.field_element(field);
+    let alpha1 = rng.field_element(field);
+    let masks = rng.field_elements(field, INPUTS);
+    let a = rng.field_element(field);
+    let b = rng.field_element(field);
+
+    let (mut masks1, mut masks2) = (Vec::with_capacity(INPUTS), Vec::with_capacity(INPUTS));
+    for &r in &masks {
+        let (s1, s2) = authenticate(field, rng, alpha, r);
+        masks1.push(s1);
+        masks2.push(s2);
+    }
+    let (a1, a2) = authenticate(field, rng, alpha, a);
+    let (b1, b2) = authenticate(field, rng, alpha, b);
+    let (c1, c2) = authenticate(field, rng, alpha, field.mul(a, b));
+
+    (
+        Preprocessing {
+            alpha_share: alpha1,
+            input_masks: masks1,
+            own_masks: masks[..INPUTS_PER_CLIENT].to_vec(),
+            triple: [a1, b1, c1],
+        },
+        Preprocessing {
+            alpha_share: field.sub(alpha, alpha1),
+            input_masks: masks2,
+            own_masks: masks[INPUTS_PER_CLIENT..].to_vec(),
+            triple: [a2, b2, c2],
+        },
+        alpha,
+    )
+}
+
+/// Beaver multiplication: our share of `x * y` given the opened `d = x - a`
+/// and `e = y - b`.
+pub fn beaver(field: &Field, me: usize, alpha_share: u64, triple: &[AuthShare; 3], d: u64, e: u64) -> AuthShare {
+    let [a, b, c] = *triple;
+    c.add(field, b.scale(field, d))
+        .add(field, a.scale(field, e))
+        .add_const(field, me, alpha_share, field.mul(d, e))
+}
@@ -0,0 +1,78 @@
+//! Aggregate statistics over a dataset that is additively shared between the
+//! two clients: record `i` is `a[i] + b[i]`, with client1 holding `a` and
+//! client2 holding `b`. The sum is local to each client, while the sum of
+//! squares needs the cross term `2 * sum(a[i] * b[i])`, which is one batched
+//! Du-Atallah inner product. Only the opened sum and sum of squares leave the
+//! clients, and the server turns them into mean and variance.
+
+use std::fmt;
+
+use crate::field::{Field, MERSENNE_61};
+
+/// Field used for statistics, large enough that sums of squares of realistic
+/// datasets do not wrap around.
+pub const STATS_FIELD: Field = Field::new(MERSENNE_61);
+
+/// One client's additive shares of the dataset's sum and sum of squares.
+#[derive(PartialEq, Eq)]
+pub struct MomentShares {
+    pub sum: u64,
+    pub sum_of_squares: u64,
+}
+
+impl fmt::Debug for MomentShares {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("MomentShares { sum: <redacted>, sum_of_squares: <redacted> }")
+    }
+}
+
+impl MomentShares {
+    /// Combine a client's local values with its share of the cross-term inner product.
+    pub fn new(field: &Field, values: &[u64], cross_share: u64) -> Self {
+        MomentShares {
+            sum: field.sum(values.iter().copied()),
+            sum_of_squares: field.add(
+                field.sum(values.iter().map(|&v| field.mul(v, v))),
+                field.mul(2, cross_share),
+            ),
+        }
+    }
+}
+
+/// Opened statistics of the combined dataset.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Statistics {
+    pub count: usize,
+    pub sum: u64,
+    pub sum_of_squares: u64,
+}
+
+impl Statistics {
+    /// Open the statistics from both clients' shares.
+    pub fn open(field: &Field, count: usize, client1: MomentShares, client2: MomentShares) -> Self {
+        Statistics {
+            count,
+            sum: field.add(client1.sum, client2.sum),
+            sum_of_squares: field.add(client1.sum_of_squares, client2.sum_of_squares),
+        }
+    }
+
+    pub fn mean(&self) -> f64 {
+        self.sum as f64 / self.count as f64
+    }
+
+    /// Population variance, `(n * sum(v^2) - sum(v)^2) / n^2`. The fraction is
+    /// computed exactly and only the quotient becomes a float, so large values
+    /// close together do not cancel to zero.
+    pub fn variance(&self) -> f64 {
+        if self.count == 0 {
+            return f64::NAN;
+        }
+        let n = self.count as u128;
+        let numerator = (n * self.sum_of_squares as u128) as i128 - (self.sum as u128 * self.sum as u128) as i128;
+        let denominator = n * n;
+        let (quotient, remainder) = (numerator.unsigned_abs() / denominator, numerator.unsigned_abs() % denominator);
+        let magnitude = quotient as f64 + remainder as f64 / denominator as f64;
+        if numerator < 0 { -magnitude } else { magnitude }
+    }
+}
@@ -0,0 +1,130 @@
+//! Byte streams the roles talk over. A `Link` is one end of a bidirectional
+//! line-based connection, backed either by a Unix socket or by an in-memory
+//! pipe whose traffic is recorded in a `Transcript`.
+
+use std::io;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+use tokio::io::{AsyncBufRead, AsyncWrite, BufReader};
+use tokio::net::UnixStream;
+
+use crate::resume;
+
+pub type Reader = Box<dyn AsyncBufRead + Unpin + Send>;
+pub type Writer = Box<dyn AsyncWrite + Unpin + Send>;
+
+/// Capacity of each direction of an in-memory link.
+const IN_MEMORY_BUFFER: usize = 1 << 20;
+
+/// One end of a connection to a peer.
+pub struct Link {
+    pub reader: Reader,
+    pub writer: Writer,
+    /// Set on resumable links, see [`crate::resume`].
+    pub session: Option<resume::SessionSlot>,
+}
+
+impl Link {
+    pub fn from_unix(stream: UnixStream) -> Self {
+        let (read, write) = stream.into_split();
+        Link {
+            reader: Box::new(BufReader::new(read)),
+            writer: Box::new(write),
+            session: None,
+        }
+    }
+
+    /// Two connected in-memory ends for roles `a` and `b`, recording every
+    /// line either side writes into `transcript`.
+    pub fn in_memory(a: &str, b: &str, transcript: &Transcript) -> (Link, Link) {
+        let (a_end, b_end) = tokio::io::duplex(IN_MEMORY_BUFFER);
+        let (a_read, a_write) = tokio::io::split(a_end);
+        let (b_read, b_write) = tokio::io::split(b_end);
+        (
+            Link {
+                reader: Box::new(BufReader::new(a_read)),
+                writer: Box::new(Recorder::new(Box::new(a_write), a, b, transcript)),
+                session: None,
+            },
+            Link {
+                reader: Box::new(BufReader::new(b_read)),
+                writer: Box::new(Recorder::new(Box::new(b_write), b, a, transcript)),
+                session: None,
+            },
+        )
+    }
+}
+
+/// A single line sent from one role to another.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Message {
+    pub from: String,
+    pub to: String,
+    pub line: String,
+}
+
+/// Shared, append-only log of the messages sent over in-memory links.
+#[derive(Debug, Clone, Default)]
+pub struct Transcript {
+    messages: Arc<Mutex<Vec<Message>>>,
+}
+
+impl Transcript {
+    pub fn messages(&self) -> Vec<Message> {
+        self.messages.lock().unwrap().clone()
+    }
+
+    fn push(&self, message: Message) {
+        self.messages.lock().unwrap().push(message);
+    }
+}
+
+/// Writer that forwards to `inner` and logs each completed line.
+struct Recorder {
+    inner: Writer,
+    from: String,
+    to: String,
+    transcript: Transcript,
+    pending: Vec<u8>,
+}
+
+impl Recorder {
+    fn new(inner: Writer, from: &str, to: &str, transcript: &Transcript) -> Self {
+        Recorder {
+            inner,
+            from: from.to_string(),
+            to: to.to_string(),
+            transcript: transcript.clone(),
+            pending: Vec::new(),
+        }
+    }
+}
+
+impl AsyncWrite for Recorder {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        let written = Pin::new(&mut this.inner).poll_write(cx, buf);
+        if let Poll::Ready(Ok(n)) = written {
+            this.pending.extend_from_slice(&buf[..n]);
+            while let Some(end) = this.pending.iter().position(|&b| b == b'\n') {
+                let line: Vec<u8> = this.pending.drain(..=end).collect();
+                this.transcript.push(Message {
+                    from: this.from.clone(),
+                    to: this.to.clone(),
+                    line: String::from_utf8_lossy(&line[..end]).into_owned(),
+                });
+            }
+        }
+        written
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_shutdown(cx)
+    }
+}
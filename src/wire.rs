@@ -0,0 +1,93 @@
+//! Line-based helpers for talking to a peer with the protocol's read timeout.
+
+use std::fmt;
+use std::io::ErrorKind;
+use std::str::FromStr;
+
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::time::{timeout, Duration};
+use zeroize::Zeroizing;
+
+use crate::error::{Phase, ProtocolError};
+
+pub const READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Read one non-empty line from `peer`, trimmed.
+pub async fn read_line<R: AsyncBufRead + Unpin>(reader: &mut R, peer: &str) -> anyhow::Result<String> {
+    read_line_within(reader, peer, READ_TIMEOUT).await
+}
+
+/// Read one non-empty line from `peer`, trimmed, waiting at most `deadline`.
+pub async fn read_line_within<R: AsyncBufRead + Unpin>(reader: &mut R, peer: &str, deadline: Duration) -> anyhow::Result<String> {
+    // Lines carry inputs and shares, so wipe the buffer once we are done with it
+    let mut buf = Zeroizing::new(String::new());
+    match timeout(deadline, reader.read_line(&mut buf)).await {
+        Ok(Ok(0)) => return Err(ProtocolError::disconnected(peer).into()),
+        Ok(Ok(_)) => {
+            if buf.trim().is_empty() {
+                return Err(ProtocolError::Malformed(format!("{} sent empty data", peer)).into());
+            }
+        },
+        Ok(Err(e)) => return Err(e.into()),
+        Err(_) => return Err(ProtocolError::timeout(Phase::Read, peer).into())
+    }
+    Ok(buf.trim().to_string())
+}
+
+/// Read a single field element from `peer`.
+pub async fn read_value<R: AsyncBufRead + Unpin>(reader: &mut R, peer: &str) -> anyhow::Result<u64> {
+    read_parsed(reader, peer).await
+}
+
+/// Read a space-separated vector of field elements sent on one line by `peer`.
+pub async fn read_values<R: AsyncBufRead + Unpin>(reader: &mut R, peer: &str) -> anyhow::Result<Vec<u64>> {
+    let line = Zeroizing::new(read_line(reader, peer).await?);
+    line.split_whitespace()
+        .map(|v| parse(v, peer))
+        .collect()
+}
+
+/// Parse a value `peer` sent, such as a field element, count or session ID.
+pub fn parse<T: FromStr>(value: &str, peer: &str) -> anyhow::Result<T>
+where
+    T::Err: fmt::Display,
+{
+    value
+        .parse()
+        .map_err(|e| ProtocolError::Malformed(format!("{} sent a malformed value: {}", peer, e)).into())
+}
+
+/// Read one line from `peer` and parse it with [`parse`].
+pub async fn read_parsed<T: FromStr, R: AsyncBufRead + Unpin>(reader: &mut R, peer: &str) -> anyhow::Result<T>
+where
+    T::Err: fmt::Display,
+{
+    let line = read_line(reader, peer).await?;
+    parse(&line, peer)
+}
+
+/// Write `line` followed by a newline to `peer` and flush it.
+pub async fn write_line<W: AsyncWrite + Unpin>(writer: &mut W, peer: &str, line: &str) -> anyhow::Result<()> {
+    write_line_within(writer, peer, line, READ_TIMEOUT).await
+}
+
+/// Write `line` followed by a newline to `peer` and flush it, giving up after `deadline`.
+pub async fn write_line_within<W: AsyncWrite + Unpin>(writer: &mut W, peer: &str, line: &str, deadline: Duration) -> anyhow::Result<()> {
+    let send = async {
+        writer.write_all(format!("{}\n", line).as_bytes()).await?;
+        writer.flush().await
+    };
+    match timeout(deadline, send).await {
+        Ok(Ok(_)) => Ok(()),
+        // The peer's end is gone, the same as reading end of file from it
+        Ok(Err(e)) if matches!(e.kind(), ErrorKind::BrokenPipe | ErrorKind::ConnectionReset) => Err(ProtocolError::disconnected(peer).into()),
+        Ok(Err(e)) => Err(e.into()),
+        Err(_) => Err(ProtocolError::timeout(Phase::Write, peer).into())
+    }
+}
+
+/// Write a vector of field elements to `peer` as one space-separated line.
+pub async fn write_values<W: AsyncWrite + Unpin>(writer: &mut W, peer: &str, values: &[u64]) -> anyhow::Result<()> {
+    let line = values.iter().map(u64::to_string).collect::<Vec<_>>().join(" ");
+    write_line(writer, peer, &line).await
+}
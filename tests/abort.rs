@@ -0,0 +1,71 @@
+//! A role that fails tells its peers, who stop at once with the original cause.
+
+use std::io::Cursor;
+use std::time::{Duration, Instant};
+
+use du_attalah::abort;
+use du_attalah::error::{self, ProtocolError};
+use du_attalah::input::Input;
+use du_attalah::operation::Operation;
+use du_attalah::randomness::{RandomSource, SeededRandom};
+use du_attalah::rendezvous::Topology;
+use du_attalah::report::Report;
+use du_attalah::roles::{client1, client2, server};
+use du_attalah::transport::{Link, Transcript};
+use du_attalah::wire;
+
+fn aborted_by(result: anyhow::Result<impl Sized>) -> (String, String) {
+    let err = result.err().expect("role should have failed");
+    match error::protocol_error(&err) {
+        Some(ProtocolError::PeerAborted { origin, reason }) => (origin.clone(), reason.clone()),
+        _ => panic!("expected an abort, got {:#}", err),
+    }
+}
+
+#[tokio::test]
+async fn bad_input_on_client2_aborts_everyone_immediately() {
+    let transcript = Transcript::default();
+    let (server1, mut to_server1) = Link::in_memory("server", "client1", &transcript);
+    let (server2, mut to_server2) = Link::in_memory("server", "client2", &transcript);
+    let (to_client2, to_client1) = Link::in_memory("client1", "client2", &transcript);
+
+    let started = Instant::now();
+    let server = async move {
+        let mut links = [server1, server2];
+        server::run(&mut links, &Topology::Direct, &mut SeededRandom::new(3, 0), &mut Report::new("server")).await
+    };
+    let client1 = async move {
+        let mut input: Input = Box::new(Cursor::new(b"3\n4\n".to_vec()));
+        let peer = async move |_: &mut dyn RandomSource, _| Ok(to_client2);
+        client1::run(Operation::Multiply, &mut to_server1, &mut input, &mut SeededRandom::new(3, 1), &mut Report::new("client1"), "memory", peer).await
+    };
+    let client2 = async move {
+        let mut input: Input = Box::new(Cursor::new(b"five\n6\n".to_vec()));
+        let peer = async move |_: &mut dyn RandomSource, _| Ok(to_client1);
+        client2::run(Operation::Multiply, &mut to_server2, &mut input, &mut SeededRandom::new(3, 2), &mut Report::new("client2"), "memory", peer).await
+    };
+    let (outcome, share1, share2) = tokio::join!(server, client1, client2);
+    assert!(started.elapsed() < Duration::from_secs(5));
+
+    let cause = share2.unwrap_err();
+    assert_eq!(error::protocol_error(&cause).map(ProtocolError::kind), Some("invalid_input"));
+    for (origin, reason) in [aborted_by(outcome), aborted_by(share1)] {
+        assert_eq!(origin, "client2");
+        assert_eq!(reason, "x is not a natural number");
+    }
+}
+
+#[test]
+fn forwarded_abort_keeps_its_origin() {
+    let first = abort::check(&abort::line("client2", &anyhow::anyhow!("Timeout waiting for Client1 data"))).unwrap_err();
+    let forwarded = abort::line("server", &first);
+    assert_eq!(forwarded, "abort client2 Timeout waiting for Client1 data");
+    assert_eq!(first.to_string(), "Session aborted by client2: Timeout waiting for Client1 data");
+    assert!(abort::check("12345").is_ok());
+}
+
+#[tokio::test]
+async fn input_that_looks_like_an_abort_is_just_input() {
+    let mut input = Cursor::new(b"abort client2 because\n".to_vec());
+    assert_eq!(wire::read_line(&mut input, "input").await.unwrap(), "abort client2 because");
+}
@@ -0,0 +1,82 @@
+//! The link handshake accepts the expected peer, rejects anyone else and
+//! leaves the link encrypted.
+
+use du_attalah::auth::{handshake, Identity};
+use du_attalah::error::{self, ProtocolError};
+use du_attalah::randomness::SeededRandom;
+use du_attalah::transport::{Link, Transcript};
+use du_attalah::wire;
+
+async fn run(server: &Identity, client: &Identity, expected_client: &Identity) -> (anyhow::Result<()>, anyhow::Result<()>) {
+    let (mut server_end, mut client_end) = Link::in_memory("server", "client1", &Transcript::default());
+    let mut server_rng = SeededRandom::new(1, 0);
+    let mut client_rng = SeededRandom::new(1, 1);
+    let client_key = expected_client.public_key();
+    let server_key = server.public_key();
+    // Each end hangs up once its handshake finishes, so a rejected peer
+    // sees the disconnect instead of waiting for the read timeout.
+    tokio::join!(
+        async move { handshake(&mut server_end, server, "client1", &client_key, &mut server_rng).await },
+        async move { handshake(&mut client_end, client, "server", &server_key, &mut client_rng).await },
+    )
+}
+
+#[tokio::test]
+async fn matching_keys_authenticate_both_ends() {
+    let mut rng = SeededRandom::new(7, 0);
+    let server = Identity::generate("server", &mut rng);
+    let client = Identity::generate("client1", &mut rng);
+
+    let (server_side, client_side) = run(&server, &client, &client).await;
+    server_side.unwrap();
+    client_side.unwrap();
+}
+
+#[tokio::test]
+async fn impostor_with_the_right_name_is_rejected() {
+    let mut rng = SeededRandom::new(7, 0);
+    let server = Identity::generate("server", &mut rng);
+    let client = Identity::generate("client1", &mut rng);
+    let impostor = Identity::generate("client1", &mut rng);
+
+    let (server_side, _) = run(&server, &impostor, &client).await;
+    let err = server_side.unwrap_err();
+    assert!(err.to_string().contains("bad signature"));
+    assert_eq!(error::protocol_error(&err).map(ProtocolError::kind), Some("auth_failed"));
+}
+
+#[tokio::test]
+async fn peer_claiming_another_name_is_rejected() {
+    let mut rng = SeededRandom::new(7, 0);
+    let server = Identity::generate("server", &mut rng);
+    let client2 = Identity::generate("client2", &mut rng);
+
+    let (server_side, _) = run(&server, &client2, &client2).await;
+    assert!(server_side.unwrap_err().to_string().contains("claims to be client2"));
+}
+
+#[tokio::test]
+async fn traffic_after_the_handshake_is_encrypted() {
+    let mut rng = SeededRandom::new(7, 0);
+    let server = Identity::generate("server", &mut rng);
+    let client = Identity::generate("client1", &mut rng);
+    let (server_key, client_key) = (server.public_key(), client.public_key());
+
+    let transcript = Transcript::default();
+    let (mut server_end, mut client_end) = Link::in_memory("server", "client1", &transcript);
+    let mut server_rng = SeededRandom::new(1, 0);
+    let mut client_rng = SeededRandom::new(1, 1);
+    let (a, b) = tokio::join!(
+        handshake(&mut server_end, &server, "client1", &client_key, &mut server_rng),
+        handshake(&mut client_end, &client, "server", &server_key, &mut client_rng),
+    );
+    a.unwrap();
+    b.unwrap();
+
+    wire::write_values(&mut client_end.writer, "server", &[123456789, 987654321]).await.unwrap();
+    let values = wire::read_values(&mut server_end.reader, "client1").await.unwrap();
+    assert_eq!(values, [123456789, 987654321]);
+
+    let sent: Vec<_> = transcript.messages().into_iter().map(|m| m.line).collect();
+    assert!(sent.iter().all(|line| !line.contains("123456789")));
+}
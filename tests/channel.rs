@@ -0,0 +1,50 @@
+//! Channels carry typed values and report failures against the peer they name.
+
+use std::time::{Duration, Instant};
+
+use du_attalah::channel::Channel;
+use du_attalah::error::{self, Phase, ProtocolError};
+use du_attalah::operation::Operation;
+use du_attalah::transport::{Link, Transcript};
+use uuid::Uuid;
+
+#[tokio::test]
+async fn values_arrive_as_the_type_they_were_sent_as() {
+    let transcript = Transcript::default();
+    let (mut a, mut b) = Link::in_memory("server", "client1", &transcript);
+    let (mut to_client, mut to_server) = (Channel::new(&mut a, "Client1"), Channel::new(&mut b, "Server"));
+    let session = Uuid::from_u128(3);
+
+    to_client.send(&session).await.unwrap();
+    to_client.send(&Operation::PolyEval).await.unwrap();
+    to_client.send_values(&[4, 5, 6]).await.unwrap();
+
+    assert_eq!(to_server.recv::<Uuid>().await.unwrap(), session);
+    assert_eq!(to_server.recv::<Operation>().await.unwrap(), Operation::PolyEval);
+    assert_eq!(to_server.recv_values().await.unwrap(), [4, 5, 6]);
+    assert_eq!(transcript.messages()[2].line, "4 5 6");
+}
+
+#[tokio::test]
+async fn each_call_can_set_its_own_deadline() {
+    let transcript = Transcript::default();
+    let (mut a, _b) = Link::in_memory("server", "client2", &transcript);
+    let mut to_client = Channel::new(&mut a, "Client2");
+
+    let started = Instant::now();
+    let err = to_client.recv_within::<u64>(Duration::from_millis(50)).await.unwrap_err();
+    assert!(started.elapsed() < Duration::from_secs(5));
+    assert_eq!(error::protocol_error(&err), Some(&ProtocolError::timeout(Phase::Read, "Client2")));
+}
+
+#[tokio::test]
+async fn wrong_type_is_malformed_and_names_the_peer() {
+    let transcript = Transcript::default();
+    let (mut a, mut b) = Link::in_memory("server", "client1", &transcript);
+    let (mut to_client, mut to_server) = (Channel::new(&mut a, "Client1"), Channel::new(&mut b, "Server"));
+
+    to_server.send("not a number").await.unwrap();
+    let err = to_client.recv::<i64>().await.unwrap_err();
+    assert_eq!(error::protocol_error(&err).map(ProtocolError::kind), Some("malformed"));
+    assert!(err.to_string().starts_with("Client1 sent a malformed value"), "{}", err);
+}
@@ -0,0 +1,24 @@
+//! The operation is the one positional argument, wherever it sits among the flags.
+
+use du_attalah::cli;
+use du_attalah::operation::Operation;
+
+fn args(line: &str) -> Vec<String> {
+    line.split_whitespace().map(String::from).collect()
+}
+
+#[test]
+fn flag_values_are_not_positional() {
+    assert_eq!(cli::positional_in(args("--keys keys poly")), ["poly"]);
+    assert_eq!(cli::positional_in(args("--share-file f --from-owner stats --insecure-debug")), ["stats"]);
+    assert!(cli::positional_in(args("--insecure-seed 7 --output json")).is_empty());
+}
+
+#[test]
+fn operation_comes_from_the_only_positional_argument() {
+    assert_eq!(Operation::from_positional(&cli::positional_in(args("--keys keys poly"))).unwrap(), Operation::PolyEval);
+    assert_eq!(Operation::from_positional(&[]).unwrap(), Operation::Multiply);
+    let err = Operation::from_positional(&args("poly stats")).unwrap_err();
+    assert!(err.to_string().contains("Unexpected argument: stats"), "{}", err);
+    assert!(Operation::from_positional(&args("--keys")).is_err());
+}
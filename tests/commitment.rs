@@ -0,0 +1,65 @@
+//! Parties exchange values only after committing to them, and a reveal that
+//! differs from the commitment aborts the exchange.
+
+use du_attalah::channel::Channel;
+use du_attalah::commitment;
+use du_attalah::randomness::SeededRandom;
+use du_attalah::transport::{Link, Transcript};
+use du_attalah::wire;
+use uuid::Uuid;
+
+const SESSION: Uuid = Uuid::from_u128(7);
+
+#[tokio::test]
+async fn honest_exchange_swaps_the_values() {
+    let transcript = Transcript::default();
+    let (mut a, mut b) = Link::in_memory("client1", "client2", &transcript);
+    let (mut rng1, mut rng2) = (SeededRandom::new(1, 1), SeededRandom::new(1, 2));
+
+    let (mut to_b, mut to_a) = (Channel::new(&mut a, "Client2"), Channel::new(&mut b, "Client1"));
+
+    let (from_b, from_a) = tokio::join!(
+        commitment::exchange(&mut to_b, SESSION, "Client1", &[1, 2, 3], &mut rng1),
+        commitment::exchange(&mut to_a, SESSION, "Client2", &[4, 5], &mut rng2),
+    );
+    assert_eq!(from_b.unwrap(), [4, 5]);
+    assert_eq!(from_a.unwrap(), [1, 2, 3]);
+
+    // Nothing is revealed before both commitments are on the wire
+    let lines: Vec<String> = transcript.messages().into_iter().map(|m| m.line).collect();
+    let first_reveal = lines.iter().position(|l| l.contains(' ')).unwrap();
+    assert_eq!(first_reveal, 2, "{:?}", lines);
+}
+
+#[tokio::test]
+async fn reveal_that_differs_from_the_commitment_is_rejected() {
+    let transcript = Transcript::default();
+    let (mut honest, mut cheater) = Link::in_memory("client1", "client2", &transcript);
+    let mut rng = SeededRandom::new(1, 1);
+    let mut to_cheater = Channel::new(&mut honest, "Client2");
+
+    let cheat = async {
+        let mut rng = SeededRandom::new(1, 2);
+        let (commitment, _) = commitment::commit(SESSION, "Client2", &[4, 5], &mut rng);
+        let (_, other) = commitment::commit(SESSION, "Client2", &[4, 6], &mut rng);
+        wire::write_line(&mut cheater.writer, "Client1", &commitment).await?;
+        wire::read_line(&mut cheater.reader, "Client1").await?;
+        wire::write_line(&mut cheater.writer, "Client1", &other.to_line()).await?;
+        wire::read_line(&mut cheater.reader, "Client1").await
+    };
+    let (result, _) = tokio::join!(
+        commitment::exchange(&mut to_cheater, SESSION, "Client1", &[1, 2, 3], &mut rng),
+        cheat,
+    );
+    let err = result.unwrap_err();
+    assert!(err.to_string().contains("do not match its commitment"), "{}", err);
+}
+
+#[test]
+fn commitment_is_bound_to_the_session_and_sender() {
+    let mut rng = SeededRandom::new(1, 1);
+    let (commitment, opening) = commitment::commit(SESSION, "Client1", &[1, 2], &mut rng);
+    assert!(commitment::verify(&commitment, SESSION, "Client1", &opening));
+    assert!(!commitment::verify(&commitment, Uuid::from_u128(8), "Client1", &opening));
+    assert!(!commitment::verify(&commitment, SESSION, "Client2", &opening));
+}
@@ -0,0 +1,196 @@
+//! Scaffolding shared by the integration tests.
+
+#![allow(dead_code)]
+
+use std::collections::BTreeMap;
+use std::io::{self, Cursor};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{ready, Context, Poll};
+
+use tokio::io::AsyncWrite;
+use tokio::sync::Notify;
+use tokio::task::JoinSet;
+use uuid::Uuid;
+
+use du_attalah::channel::Channel;
+use du_attalah::input::Input;
+use du_attalah::operation::Operation;
+use du_attalah::randomness::{RandomSource, SeededRandom, SERVER_STREAM};
+use du_attalah::rendezvous::{self, Topology};
+use du_attalah::report::Report;
+use du_attalah::roles::{client1, party, server};
+use du_attalah::share_file::Scheme;
+use du_attalah::simulate::{simulate, Simulation};
+use du_attalah::transport::{Link, Transcript, Writer};
+
+/// Simulate `op` with `inputs` as client1's and client2's terminal input,
+/// every role seeded from `seed`.
+pub fn simulation(op: Operation, inputs: [&str; 2], seed: u64) -> anyhow::Result<Simulation> {
+    let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+    let rngs: [Box<dyn RandomSource>; 3] = [
+        Box::new(SeededRandom::new(seed, SERVER_STREAM)),
+        Box::new(SeededRandom::new(seed, 1)),
+        Box::new(SeededRandom::new(seed, 2)),
+    ];
+    runtime.block_on(simulate(op, inputs, rngs))
+}
+
+/// [`simulation`] for a session expected to succeed.
+pub fn run(op: Operation, inputs: [&str; 2], seed: u64) -> Simulation {
+    simulation(op, inputs, seed).unwrap()
+}
+
+/// Run one `op` session between the server, an honest client1 with inputs
+/// `x0 = 3, y0 = 4` and a hand-written `client2`, all seeded, and return the
+/// opened value. `client2` gets its server and peer channels once it has
+/// announced `op`, joined the session and been paired with client1.
+pub async fn session(
+    op: Operation,
+    client2: impl AsyncFnOnce(&mut Channel<'_>, &mut Channel<'_>, Uuid) -> anyhow::Result<()>,
+) -> anyhow::Result<u64> {
+    let transcript = Transcript::default();
+    let (server1, mut to_server1) = Link::in_memory("server", "client1", &transcript);
+    let (server2, mut to_server2) = Link::in_memory("server", "client2", &transcript);
+    let (to_client2, mut to_client1) = Link::in_memory("client1", "client2", &transcript);
+
+    let server = async move {
+        let mut links = [server1, server2];
+        server::run(&mut links, &Topology::Direct, &mut SeededRandom::new(3, 0), &mut Report::new("server")).await
+    };
+    let honest = async move {
+        let mut input: Input = Box::new(Cursor::new(b"3\n4\n".to_vec()));
+        let peer = async move |_: &mut dyn RandomSource, _| Ok(to_client2);
+        client1::run(op, &mut to_server1, &mut input, &mut SeededRandom::new(3, 1), &mut Report::new("client1"), "memory", peer).await
+    };
+    let cheater = async {
+        let (mut server, mut p2p) = (Channel::new(&mut to_server2, "Server"), Channel::new(&mut to_client1, "Client1"));
+        server.send(&op).await?;
+        let session = server.recv().await?;
+        rendezvous::pair(&mut server, "memory").await?;
+        client2(&mut server, &mut p2p, session).await
+    };
+    let (outcome, honest, cheater) = tokio::join!(server, honest, cheater);
+    honest?;
+    cheater?;
+    match outcome? {
+        server::Outcome::Value(v) => Ok(v),
+        other => Err(anyhow::anyhow!("unexpected outcome {:?}", other)),
+    }
+}
+
+/// When a party leaves an n-party session.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Leave {
+    Never,
+    /// In `smul`, after the inputs are shared, before its reshared product goes out.
+    Reduction,
+    /// After computing its output share, before sending it to the server.
+    Open,
+}
+
+/// Run an n-party session for `leave.len()` parties sharing as `scheme` says,
+/// with inputs `x_k = k, y_k = k + 10`, each party leaving as `leave` says,
+/// and return the opened product.
+pub async fn parties(scheme: Scheme, leave: &[Leave]) -> anyhow::Result<u64> {
+    // The parties run as local tasks, as their futures need not be `Send`
+    tokio::task::LocalSet::new().run_until(run_parties(scheme, leave)).await
+}
+
+async fn run_parties(scheme: Scheme, leave: &[Leave]) -> anyhow::Result<u64> {
+    let n = leave.len();
+    let transcript = Transcript::default();
+    let mut peers: Vec<BTreeMap<usize, Link>> = (0..n).map(|_| BTreeMap::new()).collect();
+    for i in 1..=n {
+        for j in i + 1..=n {
+            let (to_j, to_i) = Link::in_memory(&format!("party{}", i), &format!("party{}", j), &transcript);
+            peers[i - 1].insert(j, to_j);
+            peers[j - 1].insert(i, to_i);
+        }
+    }
+
+    // What a party writes before it leaves: its input shares to every peer,
+    // or everything up to its output share to the server
+    let before_share = match scheme {
+        Scheme::Additive => 2,
+        Scheme::Shamir { .. } => 4,
+    };
+    let mut links = Vec::new();
+    let mut tasks = JoinSet::new();
+    for ((k, links_k), &leave_k) in (1..).zip(peers).zip(leave) {
+        let (server_end, party_end) = Link::in_memory("server", &format!("party{}", k), &transcript);
+        links.push(server_end);
+        let left = Arc::new(Notify::new());
+        let (to_peers, to_server) = match leave_k {
+            Leave::Never => (usize::MAX, usize::MAX),
+            Leave::Reduction => (2, usize::MAX),
+            Leave::Open => (usize::MAX, before_share),
+        };
+        let links_k: BTreeMap<usize, Link> = links_k.into_iter().map(|(peer, link)| (peer, stop_after(link, to_peers, &left))).collect();
+        let mut server_link = stop_after(party_end, to_server, &left);
+        tasks.spawn_local(async move {
+            let mut input: Input = Box::new(Cursor::new(format!("{}\n{}\n", k, k + 10).into_bytes()));
+            let mut rng = SeededRandom::new(9, k as u64);
+            let mut report = Report::new(&format!("party{}", k));
+            let peers = move |_: &mut dyn RandomSource| async move { Ok(links_k) };
+            tokio::select! {
+                result = party::run(k, n, scheme, &mut server_link, &mut input, &mut rng, &mut report, peers) => result.map(drop),
+                _ = left.notified() => Ok(()),
+            }
+        });
+    }
+
+    let outcome = server::run(&mut links, &Topology::Direct, &mut SeededRandom::new(9, SERVER_STREAM), &mut Report::new("server")).await;
+    drop(links);
+    while let Some(result) = tasks.join_next().await {
+        result.unwrap()?;
+    }
+    match outcome? {
+        server::Outcome::Value(v) => Ok(v),
+        other => Err(anyhow::anyhow!("unexpected outcome {:?}", other)),
+    }
+}
+
+/// `(1 + ... + n) * (11 + ... + (n + 10))`, what [`parties`] opens.
+pub fn parties_product(parties: u64) -> u64 {
+    let x: u64 = (1..=parties).sum();
+    x * (x + 10 * parties)
+}
+
+/// `link` with a writer that hangs once it has written `lines` lines and wakes
+/// `left`, as if its party stopped right there.
+fn stop_after(link: Link, lines: usize, left: &Arc<Notify>) -> Link {
+    let Link { reader, writer, session } = link;
+    Link { reader, writer: Box::new(StopAfter { inner: writer, lines, left: left.clone() }), session }
+}
+
+struct StopAfter {
+    inner: Writer,
+    lines: usize,
+    left: Arc<Notify>,
+}
+
+impl AsyncWrite for StopAfter {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        if this.lines == 0 {
+            this.left.notify_one();
+            return Poll::Pending;
+        }
+        // Pass on at most one line at a time so the count stays exact
+        let end = buf.iter().position(|&b| b == b'\n').map_or(buf.len(), |i| i + 1);
+        let written = ready!(Pin::new(&mut this.inner).poll_write(cx, &buf[..end]))?;
+        if written > 0 && buf[written - 1] == b'\n' {
+            this.lines -= 1;
+        }
+        Poll::Ready(Ok(written))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_shutdown(cx)
+    }
+}
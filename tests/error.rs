@@ -0,0 +1,82 @@
+//! Failures surface as typed `ProtocolError`s with their own exit codes.
+
+use du_attalah::commitment::Opening;
+use du_attalah::error::{self, Phase, ProtocolError};
+use du_attalah::operation::Operation;
+use du_attalah::secret::Secret;
+use du_attalah::share_file::{Scheme, ShareFile};
+use du_attalah::transport::{Link, Transcript};
+use du_attalah::wire;
+
+#[test]
+fn bad_secret_is_invalid_input_not_a_panic() {
+    let err = Secret::from_strings("12", "-3").unwrap_err();
+    let protocol = error::protocol_error(&err).unwrap();
+    assert_eq!(protocol, &ProtocolError::InvalidInput("y is not a natural number".into()));
+    assert_eq!(protocol.exit_code(), 2);
+}
+
+#[tokio::test]
+async fn closed_link_is_a_disconnect_of_that_peer() {
+    let transcript = Transcript::default();
+    let (mut server, client) = Link::in_memory("server", "client1", &transcript);
+    drop(client);
+
+    let err = wire::read_line(&mut server.reader, "Client1").await.unwrap_err();
+    assert_eq!(error::protocol_error(&err), Some(&ProtocolError::disconnected("Client1")));
+    assert_eq!(err.to_string(), "Client1 disconnected");
+}
+
+#[tokio::test]
+async fn unparsable_value_is_malformed() {
+    let transcript = Transcript::default();
+    let (mut server, mut client) = Link::in_memory("server", "client1", &transcript);
+    wire::write_line(&mut client.writer, "server", "twelve").await.unwrap();
+
+    let err = wire::read_value(&mut server.reader, "Client1").await.unwrap_err();
+    let protocol = error::protocol_error(&err).unwrap();
+    assert_eq!(protocol.kind(), "malformed");
+    assert!(err.to_string().starts_with("Client1 sent a malformed value"), "{}", err);
+}
+
+#[test]
+fn unparsable_reveal_and_share_file_are_malformed() {
+    let reveal = format!("{} 1 two", "00".repeat(32));
+    let Err(err) = Opening::from_line("Client2", &reveal) else { panic!("parsed {}", reveal) };
+    assert_eq!(error::protocol_error(&err).unwrap().kind(), "malformed", "{}", err);
+
+    let file = ShareFile {
+        session: uuid::Uuid::nil(),
+        operation: Operation::Multiply,
+        modulus: 7,
+        scheme: Scheme::Additive,
+        parties: 2,
+        party: 1,
+        values: vec![3],
+    };
+    for (from, to) in [("party=1", "party=one"), ("values=3", "values=3 x"), ("operation=mul", "operation=div")] {
+        let err = ShareFile::parse(&file.to_text().replace(from, to)).unwrap_err();
+        assert_eq!(error::protocol_error(&err).unwrap().kind(), "malformed", "{}", err);
+    }
+
+    let err = ShareFile::read("/nonexistent/share").unwrap_err();
+    assert_eq!(error::protocol_error(&err).unwrap().kind(), "invalid_input", "{}", err);
+}
+
+#[test]
+fn every_kind_has_its_own_exit_code() {
+    let errors = [
+        ProtocolError::timeout(Phase::Read, "Client2"),
+        ProtocolError::disconnected("Client2"),
+        ProtocolError::Malformed(String::new()),
+        ProtocolError::InvalidInput(String::new()),
+        ProtocolError::Aborted(String::new()),
+        ProtocolError::AuthFailed(String::new()),
+    ];
+    let mut codes: Vec<u8> = errors.iter().map(ProtocolError::exit_code).collect();
+    codes.sort();
+    codes.dedup();
+    assert_eq!(codes.len(), errors.len());
+    assert!(!codes.contains(&0) && !codes.contains(&1));
+    assert_eq!(errors[0].to_string(), "Timeout waiting for Client2 data");
+}
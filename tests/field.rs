@@ -0,0 +1,13 @@
+//! Moduli from the command line or a file must leave something to reduce into.
+
+use du_attalah::error::{self, ProtocolError};
+use du_attalah::field::Field;
+
+#[test]
+fn modulus_below_two_is_invalid_input() {
+    for modulus in [0, 1] {
+        let err = Field::try_new(modulus).unwrap_err();
+        assert!(matches!(error::protocol_error(&err), Some(ProtocolError::InvalidInput(_))), "{}", err);
+    }
+    assert_eq!(Field::try_new(2).unwrap().modulus(), 2);
+}
@@ -0,0 +1,88 @@
+//! In `mmul`, a client that deviates from the protocol makes the server's MAC
+//! check fail instead of silently changing the product.
+
+mod common;
+
+use uuid::Uuid;
+
+use du_attalah::channel::Channel;
+use du_attalah::commitment;
+use du_attalah::field::Field;
+use du_attalah::operation::Operation;
+use du_attalah::randomness::SeededRandom;
+use du_attalah::spdz::{self, AuthShare, Preprocessing};
+
+#[derive(Clone, Copy)]
+enum Cheat {
+    None,
+    /// Open a different `d` share to client1 than the one used locally.
+    OpenedValue,
+    /// Add to the output share reported to the server.
+    Output,
+}
+
+/// Client2's side of `mmul` for inputs `x1 = 5, y1 = 6`, with an optional deviation.
+async fn client2(server: &mut Channel<'_>, client1: &mut Channel<'_>, session: Uuid, cheat: Cheat) -> anyhow::Result<()> {
+    let field = Field::default();
+    let mut rng = SeededRandom::new(3, 2);
+
+    let pre = Preprocessing::from_values(&server.recv_values().await?)?;
+    let masked = [field.sub(5, pre.own_masks[0]), field.sub(6, pre.own_masks[1])];
+    let peer_masked = commitment::exchange(client1, session, "Client2", &masked, &mut rng).await?;
+    let all: Vec<u64> = peer_masked.iter().chain(&masked).copied().collect();
+    let inputs: Vec<AuthShare> = pre
+        .input_masks
+        .iter()
+        .zip(&all)
+        .map(|(mask, &m)| mask.add_const(&field, 2, pre.alpha_share, m))
+        .collect();
+
+    let [a, b, _] = pre.triple;
+    let d_share = inputs[0].add(&field, inputs[2]).sub(&field, a);
+    let e_share = inputs[1].add(&field, inputs[3]).sub(&field, b);
+    let sent_d = match cheat {
+        Cheat::OpenedValue => field.add(d_share.value, 1),
+        _ => d_share.value,
+    };
+    let peer_de = commitment::exchange(client1, session, "Client2", &[sent_d, e_share.value], &mut rng).await?;
+    let d = field.add(d_share.value, peer_de[0]);
+    let e = field.add(e_share.value, peer_de[1]);
+    let product = spdz::beaver(&field, 2, pre.alpha_share, &pre.triple, d, e);
+
+    let blinding = client1.recv_values().await?;
+    let mut report = [
+        product.value,
+        product.mac,
+        d_share.check_term(&field, pre.alpha_share, d),
+        e_share.check_term(&field, pre.alpha_share, e),
+    ];
+    if let Cheat::Output = cheat {
+        report[0] = field.add(report[0], 1);
+    }
+    let report: Vec<u64> = report.iter().zip(&blinding).map(|(&v, &r)| field.sub(v, r)).collect();
+    server.send_values(&report).await
+}
+
+async fn session(cheat: Cheat) -> anyhow::Result<u64> {
+    common::session(Operation::MaliciousMultiply, async move |server: &mut Channel<'_>, client1: &mut Channel<'_>, session| {
+        client2(server, client1, session, cheat).await
+    })
+    .await
+}
+
+#[tokio::test]
+async fn honest_clients_pass_the_mac_check() {
+    assert_eq!(session(Cheat::None).await.unwrap(), 80);
+}
+
+#[tokio::test]
+async fn tampered_opening_is_detected() {
+    let err = session(Cheat::OpenedValue).await.unwrap_err();
+    assert!(err.to_string().contains("MAC check failed"), "{}", err);
+}
+
+#[tokio::test]
+async fn tampered_output_share_is_detected() {
+    let err = session(Cheat::Output).await.unwrap_err();
+    assert!(err.to_string().contains("MAC check failed"), "{}", err);
+}
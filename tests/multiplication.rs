@@ -0,0 +1,159 @@
+//! The reconstructed product must equal `(x0 + x1) * (y0 + y1) mod p` for
+//! any inputs, including values at the edges of the field and of `u64`.
+
+mod common;
+
+use num_bigint::BigUint;
+use num_traits::ToPrimitive;
+use proptest::prelude::*;
+
+use common::{run, Leave};
+use du_attalah::du_atallah;
+use du_attalah::field::{Field, MERSENNE_61, PRIME_MOD};
+use du_attalah::nparty;
+use du_attalah::operation::Operation;
+use du_attalah::randomness::{SeededRandom, SERVER_STREAM};
+use du_attalah::roles::server::Outcome;
+use du_attalah::share_file::Scheme;
+use du_attalah::stats::STATS_FIELD;
+
+const FIELDS: [Field; 2] = [Field::new(PRIME_MOD), Field::new(MERSENNE_61)];
+
+/// Expected product, computed with arbitrary precision.
+fn expected(modulus: u64, x0: u64, x1: u64, y0: u64, y1: u64) -> u64 {
+    let x = BigUint::from(x0) + x1;
+    let y = BigUint::from(y0) + y1;
+    ((x * y) % modulus).to_u64().unwrap()
+}
+
+/// Values that tend to break modular arithmetic in `modulus`, mixed with uniform ones.
+fn value(modulus: u64) -> impl Strategy<Value = u64> {
+    prop_oneof![
+        Just(0),
+        Just(1),
+        Just(modulus - 1),
+        Just(modulus),
+        Just(modulus + 1),
+        Just((1 << 63) - 1),
+        Just(1 << 63),
+        Just((1 << 63) + 1),
+        Just(u64::MAX),
+        any::<u64>(),
+    ]
+}
+
+fn run_mul(x0: u64, y0: u64, x1: u64, y1: u64, seed: u64) -> u64 {
+    run_product(Operation::Multiply, x0, y0, x1, y1, seed)
+}
+
+fn run_product(op: Operation, x0: u64, y0: u64, x1: u64, y1: u64, seed: u64) -> u64 {
+    let input1 = format!("{}\n{}\n", x0, y0);
+    let input2 = format!("{}\n{}\n", x1, y1);
+    match run(op, [&input1, &input2], seed).outcome {
+        Outcome::Value(v) => v,
+        other => panic!("unexpected outcome {:?}", other),
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn mul_session_opens_the_product(
+        x0 in value(PRIME_MOD),
+        y0 in value(PRIME_MOD),
+        x1 in value(PRIME_MOD),
+        y1 in value(PRIME_MOD),
+        seed in any::<u64>(),
+    ) {
+        prop_assert_eq!(run_mul(x0, y0, x1, y1, seed), expected(PRIME_MOD, x0, x1, y0, y1));
+    }
+
+    #[test]
+    fn mmul_session_opens_the_product(
+        x0 in value(PRIME_MOD),
+        y0 in value(PRIME_MOD),
+        x1 in value(PRIME_MOD),
+        y1 in value(PRIME_MOD),
+        seed in any::<u64>(),
+    ) {
+        let product = run_product(Operation::MaliciousMultiply, x0, y0, x1, y1, seed);
+        prop_assert_eq!(product, expected(PRIME_MOD, x0, x1, y0, y1));
+    }
+
+    /// With one record per client, the opened sum of squares is `(a + b)^2`
+    /// computed end to end in the statistics field.
+    #[test]
+    fn stats_session_opens_the_square(a in value(MERSENNE_61), b in value(MERSENNE_61), seed in any::<u64>()) {
+        let input1 = format!("{}\n", a);
+        let input2 = format!("{}\n", b);
+        let Outcome::Statistics(stats) = run(Operation::Stats, [&input1, &input2], seed).outcome else {
+            panic!("stats session did not open statistics");
+        };
+        let a = STATS_FIELD.reduce(a);
+        let b = STATS_FIELD.reduce(b);
+        prop_assert_eq!(stats.sum_of_squares, expected(MERSENNE_61, a, b, a, b));
+    }
+
+    /// The shares two parties compute from dealt correlations add up to the
+    /// product of their additively shared inputs, in every supported field.
+    #[test]
+    fn shared_product_in_every_field(
+        x0 in any::<u64>(),
+        y0 in any::<u64>(),
+        x1 in any::<u64>(),
+        y1 in any::<u64>(),
+        seed in any::<u64>(),
+    ) {
+        for field in FIELDS {
+            let [x0, y0, x1, y1] = [x0, y0, x1, y1].map(|v| field.reduce(v));
+            let mut rng = SeededRandom::new(seed, SERVER_STREAM);
+            let (corr1, corr2) = du_atallah::deal(&field, &mut rng, nparty::PRODUCTS_PER_PAIR);
+
+            let masked1 = du_atallah::mask(&field, &nparty::cross_vector(1, 2, x0, y0), &corr1);
+            let masked2 = du_atallah::mask(&field, &nparty::cross_vector(2, 1, x1, y1), &corr2);
+            let cross1 = nparty::cross_share(&field, 1, 2, x0, y0, &masked2, &corr1);
+            let cross2 = nparty::cross_share(&field, 2, 1, x1, y1, &masked1, &corr2);
+
+            let share1 = nparty::output_share(&field, x0, y0, &[cross1]);
+            let share2 = nparty::output_share(&field, x1, y1, &[cross2]);
+            prop_assert_eq!(field.add(share1, share2), expected(field.modulus(), x0, x1, y0, y1));
+        }
+    }
+}
+
+#[test]
+fn mul_session_edge_cases() {
+    let edges = [0, 1, PRIME_MOD - 1, PRIME_MOD, (1 << 63) - 1, 1 << 63, u64::MAX];
+    for &x0 in &edges {
+        for &y1 in &edges {
+            for (x1, y0) in [(0, 0), (1, PRIME_MOD - 1), (u64::MAX, 1 << 63)] {
+                assert_eq!(
+                    run_mul(x0, y0, x1, y1, 7),
+                    expected(PRIME_MOD, x0, x1, y0, y1),
+                    "x0={x0} y0={y0} x1={x1} y1={y1}",
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn mul_accepts_inputs_wider_than_u64() {
+    let big = "340282366920938463463374607431768211455"; // 2^128 - 1
+    let input1 = format!("{}\n{}\n", big, 3);
+    let Outcome::Value(v) = run(Operation::Multiply, [&input1, "0\n5\n"], 1).outcome else {
+        panic!("mul session did not open a value");
+    };
+    let x = BigUint::parse_bytes(big.as_bytes(), 10).unwrap();
+    assert_eq!(v, ((x * 8u32) % PRIME_MOD).to_u64().unwrap());
+}
+
+#[tokio::test]
+async fn nmul_session_opens_the_product_of_the_sums() {
+    for parties in [3, 5] {
+        let leave = vec![Leave::Never; parties];
+        let product = common::parties(Scheme::Additive, &leave).await.unwrap();
+        assert_eq!(product, common::parties_product(parties as u64), "{} parties", parties);
+    }
+}
@@ -0,0 +1,68 @@
+//! `poly` opens `P(x)` for client1's coefficients and client2's point, and
+//! client2 refuses a polynomial with no coefficients.
+
+mod common;
+
+use std::io::Cursor;
+
+use proptest::prelude::*;
+use uuid::Uuid;
+
+use du_attalah::channel::Channel;
+use du_attalah::error::{self, ProtocolError};
+use du_attalah::field::{Field, PRIME_MOD};
+use du_attalah::input::Input;
+use du_attalah::operation::Operation;
+use du_attalah::poly;
+use du_attalah::randomness::{RandomSource, SeededRandom};
+use du_attalah::rendezvous::{Listening, Pairing};
+use du_attalah::report::Report;
+use du_attalah::roles::client2;
+use du_attalah::roles::server::Outcome;
+use du_attalah::transport::{Link, Transcript};
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(32))]
+
+    #[test]
+    fn poly_session_matches_horner(
+        coefficients in prop::collection::vec(0..PRIME_MOD, 1..8),
+        x in 0..PRIME_MOD,
+        seed in any::<u64>(),
+    ) {
+        let line: Vec<String> = coefficients.iter().map(u64::to_string).collect();
+        let input1 = format!("{}\n", line.join(" "));
+        let input2 = format!("{}\n", x);
+        let Outcome::Value(v) = common::run(Operation::PolyEval, [&input1, &input2], seed).outcome else {
+            panic!("poly session did not open a value");
+        };
+        prop_assert_eq!(v, poly::evaluate(&Field::default(), &coefficients, x));
+    }
+}
+
+#[tokio::test]
+async fn client2_refuses_an_empty_polynomial() {
+    let transcript = Transcript::default();
+    let (mut server, mut to_server) = Link::in_memory("server", "client2", &transcript);
+    let (to_client2, _to_client1) = Link::in_memory("client1", "client2", &transcript);
+
+    let fake_server = async {
+        let mut client2 = Channel::new(&mut server, "Client2");
+        let _op: Operation = client2.recv().await?;
+        client2.send(&Uuid::nil()).await?;
+        let _: Listening = client2.recv().await?;
+        client2.send(&Pairing::Accept).await?;
+        client2.send(&0).await
+    };
+    let client2 = async {
+        let mut input: Input = Box::new(Cursor::new(b"5\n".to_vec()));
+        let peer = async move |_: &mut dyn RandomSource, _| Ok(to_client2);
+        client2::run(Operation::PolyEval, &mut to_server, &mut input, &mut SeededRandom::new(3, 2), &mut Report::new("client2"), "memory", peer).await
+    };
+    let (_, result) = tokio::join!(fake_server, client2);
+    let Err(err) = result else {
+        panic!("client2 accepted an empty polynomial");
+    };
+    assert!(matches!(error::protocol_error(&err), Some(ProtocolError::Malformed(_))), "{:#}", err);
+    assert!(err.to_string().contains("empty polynomial"), "{}", err);
+}
@@ -0,0 +1,35 @@
+//! A seeded session replays exactly: same transcript, same result, same shares.
+
+mod common;
+
+use common::run;
+use du_attalah::operation::Operation;
+
+const SESSIONS: [(Operation, [&str; 2]); 4] = [
+    (Operation::Multiply, ["3\n4\n", "5\n6\n"]),
+    (Operation::PolyEval, ["1 2 3\n", "5\n"]),
+    (Operation::Stats, ["1 2 3 4\n", "1 2 3 4\n"]),
+    (Operation::MaliciousMultiply, ["3\n4\n", "5\n6\n"]),
+];
+
+#[test]
+fn same_seed_replays_the_session() {
+    for (op, inputs) in SESSIONS {
+        let first = run(op, inputs, 42);
+        let second = run(op, inputs, 42);
+        assert!(!first.transcript.is_empty());
+        assert_eq!(first.transcript, second.transcript, "{}", op);
+        assert_eq!(first.outcome, second.outcome, "{}", op);
+        assert_eq!(first.shares, second.shares, "{}", op);
+    }
+}
+
+#[test]
+fn another_seed_changes_the_transcript_but_not_the_result() {
+    for (op, inputs) in SESSIONS {
+        let first = run(op, inputs, 42);
+        let other = run(op, inputs, 43);
+        assert_ne!(first.transcript, other.transcript, "{}", op);
+        assert_eq!(first.outcome, other.outcome, "{}", op);
+    }
+}
@@ -0,0 +1,115 @@
+//! In `mul`, the clients commit to their masked values before revealing them
+//! to each other, and the server checks the masked values each client relays
+//! for the other and names the client that changed them.
+
+mod common;
+
+use uuid::Uuid;
+
+use du_attalah::auth::Identity;
+use du_attalah::channel::Channel;
+use du_attalah::error::{self, ProtocolError};
+use du_attalah::operation::Operation;
+use du_attalah::randomness::SeededRandom;
+use du_attalah::relay;
+use du_attalah::roles::state::{Message, Values};
+
+#[derive(Clone, Copy)]
+enum Cheat {
+    None,
+    /// Forward different masked values to client1 than the server sent, signed.
+    Forward,
+    /// Change client1's masked values before relaying them to the server.
+    Relay,
+    /// Reveal different masked values to client1 than the ones committed to.
+    Reveal,
+    /// Wait for client1's first message before committing to anything.
+    Peek,
+}
+
+/// Client2's side of `mul` for inputs `x1 = 5, y1 = 6`, with an optional deviation.
+async fn client2(server: &mut Channel<'_>, p2p: &mut Channel<'_>, session: Uuid, cheat: Cheat) -> anyhow::Result<()> {
+    let key = Identity::generate("client2", &mut SeededRandom::new(3, 2));
+    server.send(&Message::Shares(Values { x: 5, y: 6, auth: hex::encode(key.public_key().to_bytes()) })).await?;
+    let Message::Masks(Values { x: mx1, y: my1, .. }) = server.recv().await? else {
+        anyhow::bail!("expected masks from the server");
+    };
+
+    let forwarded = match cheat {
+        Cheat::Forward => [mx1, my1 + 1],
+        _ => [mx1, my1],
+    };
+    let signature = relay::sign(&key, session, &forwarded);
+    let commitment = Message::Commit(relay::commit(session, "client2", &forwarded, &signature));
+
+    // Client1 shows nothing but its commitment until we have sent ours
+    let peer_commitment = match cheat {
+        Cheat::Peek => {
+            let first = p2p.recv().await?;
+            assert!(matches!(first, Message::Commit(_)), "client1 revealed {} before client2 committed", first);
+            p2p.send(&commitment).await?;
+            first
+        },
+        _ => {
+            p2p.send(&commitment).await?;
+            p2p.recv().await?
+        },
+    };
+    let Message::Commit(peer_commitment) = peer_commitment else {
+        anyhow::bail!("expected a commitment from client1");
+    };
+
+    let revealed = match cheat {
+        Cheat::Reveal => [forwarded[0], forwarded[1] + 1],
+        _ => forwarded,
+    };
+    let signature = relay::sign(&key, session, &revealed);
+    p2p.send(&Message::Masked(Values { x: revealed[0], y: revealed[1], auth: signature })).await?;
+
+    let Message::Masked(Values { x: mx0, y: my0, auth: signature }) = p2p.recv().await? else {
+        anyhow::bail!("expected masked values from client1");
+    };
+    relay::open(&peer_commitment, session, "client1", &[mx0, my0], &signature)?;
+    let relayed = match cheat {
+        Cheat::Relay => [mx0, my0 + 1],
+        _ => [mx0, my0],
+    };
+    server.send(&Message::Relay(Values { x: relayed[0], y: relayed[1], auth: signature })).await
+}
+
+async fn session(cheat: Cheat) -> anyhow::Result<u64> {
+    common::session(Operation::Multiply, async move |server: &mut Channel<'_>, p2p: &mut Channel<'_>, session| {
+        client2(server, p2p, session, cheat).await
+    })
+    .await
+}
+
+#[tokio::test]
+async fn honest_relays_pass_the_check() {
+    assert_eq!(session(Cheat::None).await.unwrap(), 80);
+}
+
+#[tokio::test]
+async fn wrong_forward_is_blamed_on_the_forwarder() {
+    let err = session(Cheat::Forward).await.unwrap_err();
+    assert!(err.to_string().contains("client2 forwarded masked values"), "{}", err);
+}
+
+#[tokio::test]
+async fn tampered_relay_is_blamed_on_the_relayer() {
+    let err = session(Cheat::Relay).await.unwrap_err();
+    assert!(err.to_string().contains("client2 tampered"), "{}", err);
+    assert!(matches!(error::protocol_error(&err), Some(ProtocolError::Aborted(_))));
+}
+
+#[tokio::test]
+async fn client1_reveals_nothing_before_client2_commits() {
+    assert_eq!(session(Cheat::Peek).await.unwrap(), 80);
+}
+
+#[tokio::test]
+async fn reveal_that_differs_from_the_commitment_aborts() {
+    let err = session(Cheat::Reveal).await.unwrap_err();
+    assert!(matches!(error::protocol_error(&err), Some(ProtocolError::Aborted(_))), "{:#}", err);
+    assert!(err.to_string().contains("client2 revealed masked values that do not match its commitment"), "{}", err);
+}
@@ -0,0 +1,116 @@
+//! The server pairs the clients in the order they register, so whichever
+//! client registers last dials the one that is already waiting, or sends both
+//! through its relay in the star topology, which splices only the two clients
+//! of the same session.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{UnixListener, UnixStream};
+use uuid::Uuid;
+
+use du_attalah::channel::Channel;
+use du_attalah::error::{self, ProtocolError};
+use du_attalah::randomness::SeededRandom;
+use du_attalah::rendezvous::{self, Pairing, Ticket, Topology};
+use du_attalah::transport::{Link, Transcript};
+
+const SESSION: Uuid = Uuid::from_u128(7);
+
+async fn pairings(client2_first: bool, topology: &Topology) -> (Pairing, Pairing) {
+    let transcript = Transcript::default();
+    let (mut server1, mut to_server1) = Link::in_memory("server", "client1", &transcript);
+    let (mut server2, mut to_server2) = Link::in_memory("server", "client2", &transcript);
+    let (mut client1, mut client2) = (Channel::new(&mut server1, "Client1"), Channel::new(&mut server2, "Client2"));
+
+    let delay = |late: bool| tokio::time::sleep(Duration::from_millis(if late { 100 } else { 0 }));
+    let first = async {
+        delay(client2_first).await;
+        rendezvous::pair(&mut Channel::new(&mut to_server1, "Server"), "/tmp/one").await
+    };
+    let second = async {
+        delay(!client2_first).await;
+        rendezvous::pair(&mut Channel::new(&mut to_server2, "Server"), "/tmp/two").await
+    };
+    let (brokered, pairing1, pairing2) = tokio::join!(rendezvous::broker(&mut client1, &mut client2, SESSION, topology), first, second);
+    brokered.unwrap();
+    (pairing1.unwrap(), pairing2.unwrap())
+}
+
+#[tokio::test]
+async fn the_later_client_dials_the_earlier_one() {
+    assert_eq!(pairings(false, &Topology::Direct).await, (Pairing::Accept, Pairing::Dial("/tmp/one".into())));
+    assert_eq!(pairings(true, &Topology::Direct).await, (Pairing::Dial("/tmp/two".into()), Pairing::Accept));
+}
+
+#[tokio::test]
+async fn star_topology_sends_both_clients_to_the_relay() {
+    let relay = |client| Pairing::Relay("/tmp/relay".into(), Ticket { session: SESSION, client });
+    assert_eq!(pairings(false, &Topology::Star("/tmp/relay".into())).await, (relay(1), relay(2)));
+}
+
+/// A socket path of our own, so parallel tests and running binaries never collide.
+fn socket(name: &str) -> String {
+    std::env::temp_dir().join(format!("da_test_{}_{}", std::process::id(), name)).display().to_string()
+}
+
+/// Connect to the relay at `path` and present a ticket for `client` of `session`.
+async fn relayed(path: &str, session: u128, client: usize) -> UnixStream {
+    let mut stream = UnixStream::connect(path).await.unwrap();
+    let ticket = Ticket { session: Uuid::from_u128(session), client };
+    stream.write_all(format!("{}\n", ticket).as_bytes()).await.unwrap();
+    stream
+}
+
+#[tokio::test]
+async fn relay_splices_the_two_clients_of_a_session() {
+    let path = socket("relay");
+    let _ = std::fs::remove_file(&path);
+    tokio::spawn(rendezvous::relay(UnixListener::bind(&path).unwrap()));
+
+    let mut garbage = UnixStream::connect(&path).await.unwrap();
+    garbage.write_all(b"hello\n").await.unwrap();
+    let mut stale = relayed(&path, 1, 1).await;
+    let mut other_session = relayed(&path, 2, 2).await;
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    let client1 = relayed(&path, 1, 1).await;
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    let mut client2 = relayed(&path, 1, 2).await;
+
+    client2.write_all(b"frame\n").await.unwrap();
+    let mut line = String::new();
+    BufReader::new(client1).read_line(&mut line).await.unwrap();
+    assert_eq!(line, "frame\n");
+
+    // The replaced half and the garbage are hung up on, the other session still waits
+    assert_eq!(stale.read(&mut [0; 8]).await.unwrap(), 0);
+    assert_eq!(garbage.read(&mut [0; 8]).await.unwrap(), 0);
+    assert!(tokio::time::timeout(Duration::from_millis(50), other_session.read(&mut [0; 8])).await.is_err());
+    let _ = std::fs::remove_file(&path);
+}
+
+#[tokio::test]
+async fn relayed_link_without_keys_is_refused() {
+    let path = socket("own");
+    let _ = std::fs::remove_file(&path);
+    let listener = Arc::new(UnixListener::bind(&path).unwrap());
+    let pairing = Pairing::Relay(socket("unused"), Ticket { session: SESSION, client: 2 });
+    let Err(err) = rendezvous::open(pairing, listener, "client2", None, Duration::from_secs(1), &mut SeededRandom::new(1, 1)).await else {
+        panic!("a relayed link opened without keys");
+    };
+    assert!(matches!(error::protocol_error(&err), Some(ProtocolError::InvalidInput(_))), "{}", err);
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn pairings_round_trip_as_lines() {
+    let relay = Pairing::Relay("/tmp/da_relay".into(), Ticket { session: SESSION, client: 1 });
+    for pairing in [Pairing::Accept, Pairing::Dial("/tmp/p2p_client1".into()), relay] {
+        assert_eq!(pairing.to_string().parse::<Pairing>().unwrap(), pairing);
+    }
+    assert!("dial".parse::<Pairing>().is_err());
+    assert!("relay /tmp/da_relay".parse::<Pairing>().is_err());
+    assert!(format!("relay {} 3", SESSION).parse::<Ticket>().is_err());
+    assert!("listening".parse::<rendezvous::Listening>().is_err());
+}
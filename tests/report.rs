@@ -0,0 +1,42 @@
+//! Each role's `--output json` report names the session and what it produced.
+
+use du_attalah::operation::Operation;
+use du_attalah::randomness::{RandomSource, SeededRandom, SERVER_STREAM};
+use du_attalah::report::Report;
+use du_attalah::simulate::simulate;
+use serde_json::json;
+
+#[tokio::test]
+async fn simulated_session_reports_serialize_to_one_document_per_role() {
+    let rngs: [Box<dyn RandomSource>; 3] = [
+        Box::new(SeededRandom::new(5, SERVER_STREAM)),
+        Box::new(SeededRandom::new(5, 1)),
+        Box::new(SeededRandom::new(5, 2)),
+    ];
+    let simulation = simulate(Operation::Multiply, ["3\n4\n", "5\n6\n"], rngs).await.unwrap();
+
+    let documents: Vec<serde_json::Value> =
+        simulation.reports.iter().map(|r| serde_json::to_value(r).unwrap()).collect();
+    let session = documents[0]["session"].clone();
+    assert!(session.is_string());
+    for (document, role) in documents.iter().zip(["server", "client1", "client2"]) {
+        assert_eq!(document["role"], role);
+        assert_eq!(document["session"], session);
+        assert_eq!(document["operation"], "mul");
+        assert_eq!(document["status"], "ok");
+        assert!(document.get("error").is_none(), "{}", document);
+    }
+    assert_eq!(documents[0]["result"], json!(80));
+    assert!(documents[1].get("result").is_none());
+}
+
+#[test]
+fn reconstructed_values_report_as_a_number_or_an_array() {
+    let mut report = Report::new("owner");
+    report.reconstructed(&[30]);
+    assert_eq!(serde_json::to_value(&report).unwrap()["result"], json!(30));
+    report.reconstructed(&[4, 20, 5]);
+    let document = serde_json::to_value(&report).unwrap();
+    assert_eq!(document["result"], json!([4, 20, 5]));
+    assert!(document.get("files").is_none(), "{}", document);
+}
@@ -0,0 +1,165 @@
+//! A link that drops mid-session is resumed over a new connection without
+//! losing or repeating any line.
+
+use std::io::{self, Cursor};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::time::Duration;
+
+use tokio::io::{AsyncRead, AsyncWrite, BufReader, ReadBuf};
+use tokio::sync::mpsc;
+use tokio::time::timeout;
+
+use du_attalah::error::{self, Phase, ProtocolError};
+use du_attalah::input::Input;
+use du_attalah::operation::Operation;
+use du_attalah::randomness::{RandomSource, SeededRandom};
+use du_attalah::rendezvous::Topology;
+use du_attalah::report::Report;
+use du_attalah::resume::{self, Reconnect};
+use du_attalah::roles::{client1, client2, server};
+use du_attalah::transport::{Link, Transcript};
+use du_attalah::wire;
+
+/// Cuts both ends of a link once `after` lines have been written on it.
+struct Cut {
+    after: usize,
+    lines: AtomicUsize,
+    readers: Mutex<Vec<Waker>>,
+}
+
+impl Cut {
+    fn new(after: usize) -> Arc<Self> {
+        Arc::new(Cut { after, lines: AtomicUsize::new(0), readers: Mutex::new(Vec::new()) })
+    }
+
+    fn is_cut(&self) -> bool {
+        self.lines.load(Ordering::SeqCst) >= self.after
+    }
+}
+
+/// One direction of a link that fails like a dropped socket once cut.
+struct Flaky<T> {
+    inner: T,
+    cut: Arc<Cut>,
+}
+
+impl<T: AsyncRead + Unpin> AsyncRead for Flaky<T> {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        if this.cut.is_cut() {
+            return Poll::Ready(Ok(()));
+        }
+        this.cut.readers.lock().unwrap().push(cx.waker().clone());
+        Pin::new(&mut this.inner).poll_read(cx, buf)
+    }
+}
+
+impl<T: AsyncWrite + Unpin> AsyncWrite for Flaky<T> {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        if this.cut.is_cut() {
+            return Poll::Ready(Err(io::ErrorKind::BrokenPipe.into()));
+        }
+        let written = Pin::new(&mut this.inner).poll_write(cx, buf);
+        if let Poll::Ready(Ok(n)) = written {
+            let lines = buf[..n].iter().filter(|&&b| b == b'\n').count();
+            this.cut.lines.fetch_add(lines, Ordering::SeqCst);
+            if this.cut.is_cut() {
+                this.cut.readers.lock().unwrap().drain(..).for_each(Waker::wake);
+            }
+        }
+        written
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_shutdown(cx)
+    }
+}
+
+fn flaky(link: Link, cut: &Arc<Cut>) -> Link {
+    Link {
+        reader: Box::new(BufReader::new(Flaky { inner: link.reader, cut: cut.clone() })),
+        writer: Box::new(Flaky { inner: link.writer, cut: cut.clone() }),
+        session: None,
+    }
+}
+
+/// Reconnects for the two ends of an in-memory link: the dialer opens a new
+/// pair and hands one end to the acceptor, who waits for it up to `grace`.
+fn reconnects(a: &str, b: &str, transcript: &Transcript, grace: Duration) -> (Reconnect, Reconnect) {
+    let (dialed, accepted) = mpsc::unbounded_channel();
+    let accepted = Arc::new(tokio::sync::Mutex::new(accepted));
+    let (a, b, transcript) = (a.to_string(), b.to_string(), transcript.clone());
+    let peer = b.clone();
+    let accept: Reconnect = Box::new(move || {
+        let (accepted, peer) = (accepted.clone(), peer.clone());
+        Box::pin(async move {
+            match timeout(grace, accepted.lock().await.recv()).await {
+                Ok(Some(link)) => Ok(link),
+                _ => Err(ProtocolError::timeout(Phase::Accept, peer).into()),
+            }
+        })
+    });
+    let dial: Reconnect = Box::new(move || {
+        let (theirs, ours) = Link::in_memory(&a, &b, &transcript);
+        let _ = dialed.send(theirs);
+        Box::pin(async move { Ok(ours) })
+    });
+    (accept, dial)
+}
+
+#[tokio::test]
+async fn session_survives_a_dropped_client_link() {
+    let transcript = Transcript::default();
+    let cut = Cut::new(4);
+    let (server1, to_server1) = Link::in_memory("server", "client1", &transcript);
+    let (accept, dial) = reconnects("server", "client1", &transcript, Duration::from_secs(5));
+    let server1 = resume::resumable(flaky(server1, &cut), "client1", accept);
+    let mut to_server1 = resume::resumable(flaky(to_server1, &cut), "server", dial);
+    let (server2, mut to_server2) = Link::in_memory("server", "client2", &transcript);
+    let (to_client2, to_client1) = Link::in_memory("client1", "client2", &transcript);
+
+    let server = async move {
+        let mut links = [server1, server2];
+        server::run(&mut links, &Topology::Direct, &mut SeededRandom::new(5, 0), &mut Report::new("server")).await
+    };
+    let client1 = async move {
+        let mut input: Input = Box::new(Cursor::new(b"3\n4\n".to_vec()));
+        let peer = async move |_: &mut dyn RandomSource, _| Ok(to_client2);
+        client1::run(Operation::Multiply, &mut to_server1, &mut input, &mut SeededRandom::new(5, 1), &mut Report::new("client1"), "memory", peer).await
+    };
+    let client2 = async move {
+        let mut input: Input = Box::new(Cursor::new(b"5\n6\n".to_vec()));
+        let peer = async move |_: &mut dyn RandomSource, _| Ok(to_client1);
+        client2::run(Operation::Multiply, &mut to_server2, &mut input, &mut SeededRandom::new(5, 2), &mut Report::new("client2"), "memory", peer).await
+    };
+    let (outcome, share1, share2) = tokio::join!(server, client1, client2);
+    share1.unwrap();
+    share2.unwrap();
+    assert_eq!(outcome.unwrap(), server::Outcome::Value(80));
+    assert!(cut.is_cut(), "the link should have dropped during the session");
+}
+
+#[tokio::test]
+async fn peer_that_never_comes_back_is_a_disconnect() {
+    let transcript = Transcript::default();
+    let cut = Cut::new(2);
+    let (a, b) = Link::in_memory("server", "client1", &transcript);
+    let (accept, _dial) = reconnects("server", "client1", &transcript, Duration::from_millis(100));
+    let mut a = resume::resumable(flaky(a, &cut), "client1", accept);
+    let mut b = flaky(b, &cut);
+    resume::joined(&a.session, uuid::Uuid::new_v4());
+
+    wire::write_line(&mut b.writer, "server", "first").await.unwrap();
+    assert_eq!(wire::read_line(&mut a.reader, "Client1").await.unwrap(), "first");
+    wire::write_line(&mut b.writer, "server", "second").await.unwrap();
+    let err = wire::read_line(&mut a.reader, "Client1").await.unwrap_err();
+    assert_eq!(error::protocol_error(&err), Some(&ProtocolError::disconnected("Client1")));
+}
@@ -0,0 +1,84 @@
+//! Secret inputs reduce correctly and never show up in formatted output.
+
+use du_attalah::du_atallah::Correlation;
+use du_attalah::error::{self, ProtocolError};
+use du_attalah::field::{Field, PRIME_MOD};
+use du_attalah::operation::Operation;
+use du_attalah::secret::Secret;
+use du_attalah::shamir::Share;
+use du_attalah::share_file::{Scheme, ShareFile};
+use du_attalah::spdz::AuthShare;
+use du_attalah::stats::MomentShares;
+use uuid::Uuid;
+
+#[test]
+fn secret_is_redacted_in_debug_and_display() {
+    let secret = Secret::from_strings("123456789", "987654321").unwrap();
+    for text in [format!("{:?}", secret), format!("{}", secret)] {
+        assert!(!text.contains("123456789") && !text.contains("987654321"), "{}", text);
+    }
+}
+
+#[test]
+fn secret_reduces_inputs_of_any_size() {
+    let field = Field::new(PRIME_MOD);
+    // 10^30 mod p, with 10^9 = -7 mod p: 10^30 = 10^3 * (10^9)^3 = 1000 * -343
+    let secret = Secret::from_strings("1000000000000000000000000000000", "1000000007").unwrap();
+    assert_eq!(secret.reduce(&field), (field.neg(343_000), 0));
+    assert_eq!(Secret::new(5, 6).reduce(&field), (5, 6));
+}
+
+#[test]
+fn secret_rejects_anything_but_digits() {
+    for (x, y) in [("", "1"), ("1", "-2"), ("0x10", "1"), ("1 2", "3")] {
+        let err = Secret::from_strings(x, y).unwrap_err().to_string();
+        assert!(err.ends_with("is not a natural number"), "{}", err);
+    }
+}
+
+#[test]
+fn share_file_values_are_redacted_in_debug() {
+    let file = ShareFile {
+        session: Uuid::from_u128(1),
+        operation: Operation::PolyEval,
+        modulus: PRIME_MOD,
+        scheme: Scheme::Additive,
+        parties: 2,
+        party: 1,
+        values: vec![424242],
+    };
+    let text = format!("{:?}", file);
+    assert!(text.contains("party: 1") && !text.contains("424242"), "{}", text);
+}
+
+#[test]
+fn shares_and_correlations_are_redacted_in_debug() {
+    let texts = [
+        format!("{:?}", Share { index: 3, value: 424242 }),
+        format!("{:?}", AuthShare { value: 424242, mac: 434343 }),
+        format!("{:?}", MomentShares { sum: 424242, sum_of_squares: 434343 }),
+        format!("{:?}", Correlation { mask: 424242, offset: 434343 }),
+    ];
+    assert!(texts[0].contains("index: 3"), "{}", texts[0]);
+    for text in texts {
+        assert!(!text.contains("424242") && !text.contains("434343"), "{}", text);
+    }
+}
+
+#[test]
+fn share_file_with_a_zero_modulus_is_rejected() {
+    let file = ShareFile {
+        session: Uuid::from_u128(1),
+        operation: Operation::PolyEval,
+        modulus: PRIME_MOD,
+        scheme: Scheme::Additive,
+        parties: 2,
+        party: 1,
+        values: vec![7],
+    };
+    assert_eq!(ShareFile::parse(&file.to_text()).unwrap(), file);
+
+    let text = file.to_text().replace(&format!("modulus={}", PRIME_MOD), "modulus=0");
+    let err = ShareFile::parse(&text).unwrap_err();
+    assert!(matches!(error::protocol_error(&err), Some(ProtocolError::InvalidInput(_))), "{}", err);
+}
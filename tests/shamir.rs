@@ -0,0 +1,133 @@
+//! Any `t` Shamir shares open the secret and fewer say nothing about it,
+//! degree reduction turns reshared products into shares of `x * y`, and a
+//! `smul` session survives parties dropping out once the inputs are shared.
+
+mod common;
+
+use num_bigint::BigUint;
+use num_traits::ToPrimitive;
+use proptest::prelude::*;
+
+use common::Leave;
+use du_attalah::error::{self, ProtocolError};
+use du_attalah::field::Field;
+use du_attalah::randomness::SeededRandom;
+use du_attalah::shamir::{self, Share};
+use du_attalah::share_file::Scheme;
+
+/// A threshold, a party count that can multiply with it, and a seed.
+fn params() -> impl Strategy<Value = (usize, usize, u64)> {
+    (1..=4usize).prop_flat_map(|t| (Just(t), 2 * t - 1..=8, any::<u64>()))
+}
+
+/// `x * y mod p`, computed with arbitrary precision.
+fn product(field: &Field, x: u64, y: u64) -> u64 {
+    ((BigUint::from(x) * y) % field.modulus()).to_u64().unwrap()
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn any_threshold_shares_reconstruct(
+        (t, n, seed) in params(),
+        secret in any::<u64>(),
+        pick in any::<prop::sample::Index>(),
+    ) {
+        let field = Field::default();
+        let shares = shamir::share(&field, &mut SeededRandom::new(seed, 1), secret, t, n);
+        // Any window of `t` consecutive parties, wrapping around
+        let start = pick.index(n);
+        let subset: Vec<Share> = (0..t).map(|i| shares[(start + i) % n]).collect();
+        prop_assert_eq!(shamir::reconstruct(&field, &subset).unwrap(), field.reduce(secret));
+    }
+
+    /// For every candidate secret there is a `t`-th share that opens `t - 1`
+    /// real shares to it, so they rule nothing out.
+    #[test]
+    fn fewer_than_threshold_shares_fit_every_secret(
+        (t, n, seed) in params(),
+        secret in any::<u64>(),
+        candidate in any::<u64>(),
+    ) {
+        let field = Field::default();
+        let shares = shamir::share(&field, &mut SeededRandom::new(seed, 1), secret, t, n);
+        let mut known = shares[..t - 1].to_vec();
+        let extra = n as u64 + 1;
+        let indices: Vec<u64> = known.iter().map(|s| s.index).chain([extra]).collect();
+        let lagrange = shamir::lagrange_at_zero(&field, &indices).unwrap();
+        let partial = field.sum(known.iter().zip(&lagrange).map(|(s, &c)| field.mul(s.value, c)));
+        let value = field.mul(field.sub(field.reduce(candidate), partial), field.inv(lagrange[t - 1]).unwrap());
+        known.push(Share { index: extra, value });
+        prop_assert_eq!(shamir::reconstruct(&field, &known).unwrap(), field.reduce(candidate));
+    }
+
+    #[test]
+    fn reduced_products_open_to_the_product(
+        (t, n, seed) in params(),
+        x in any::<u64>(),
+        y in any::<u64>(),
+        skip in any::<prop::sample::Index>(),
+    ) {
+        let field = Field::default();
+        let mut rng = SeededRandom::new(seed, 1);
+        let x_shares = shamir::share(&field, &mut rng, x, t, n);
+        let y_shares = shamir::share(&field, &mut rng, y, t, n);
+        let reshares: Vec<Vec<Share>> = (0..n)
+            .map(|i| shamir::reshare_product(&field, &mut rng, shamir::mul_local(&field, x_shares[i], y_shares[i]), t, n))
+            .collect();
+
+        // Only the first 2t - 1 senders after some offset are combined
+        let start = skip.index(n);
+        let senders: Vec<usize> = (0..shamir::reduction_quorum(t)).map(|i| (start + i) % n).collect();
+        let reduced: Vec<Share> = (0..n)
+            .map(|j| {
+                let pieces: Vec<(u64, u64)> = senders.iter().map(|&i| (i as u64 + 1, reshares[i][j].value)).collect();
+                shamir::reduce_degree(&field, j as u64 + 1, &pieces).unwrap()
+            })
+            .collect();
+        let expected = product(&field, field.reduce(x), field.reduce(y));
+        prop_assert_eq!(shamir::reconstruct(&field, &reduced[..t]).unwrap(), expected);
+        prop_assert_eq!(shamir::reconstruct(&field, &reduced[n - t..]).unwrap(), expected);
+    }
+
+    #[test]
+    fn params_need_two_threshold_minus_one_parties(t in 1..=16usize, n in 0..=40usize) {
+        let result = shamir::check_params(t, n);
+        if n < 2 * t - 1 {
+            let err = result.unwrap_err();
+            prop_assert!(matches!(error::protocol_error(&err), Some(ProtocolError::InvalidInput(_))), "{}", err);
+        } else {
+            prop_assert!(result.is_ok());
+        }
+    }
+}
+
+#[test]
+fn zero_threshold_is_rejected() {
+    assert!(shamir::check_params(0, 5).is_err());
+}
+
+/// Run `smul` with `threshold` for `leave.len()` parties, each leaving as `leave` says.
+async fn smul(threshold: usize, leave: &[Leave]) -> anyhow::Result<u64> {
+    common::parties(Scheme::Shamir { threshold }, leave).await
+}
+
+#[tokio::test]
+async fn smul_opens_with_a_party_missing_at_the_open() {
+    let leave = [Leave::Never, Leave::Never, Leave::Open, Leave::Never, Leave::Never];
+    assert_eq!(smul(3, &leave).await.unwrap(), common::parties_product(5));
+}
+
+#[tokio::test]
+async fn smul_reduces_without_a_party_that_left_while_resharing() {
+    let leave = [Leave::Never, Leave::Reduction, Leave::Never, Leave::Open, Leave::Never];
+    assert_eq!(smul(2, &leave).await.unwrap(), common::parties_product(5));
+}
+
+#[tokio::test]
+async fn smul_aborts_when_too_few_reshared_products_arrive() {
+    let leave = [Leave::Never, Leave::Reduction, Leave::Never, Leave::Never, Leave::Never];
+    let err = smul(3, &leave).await.unwrap_err();
+    assert_eq!(error::protocol_error(&err), Some(&ProtocolError::disconnected("Party2")), "{}", err);
+}
@@ -0,0 +1,117 @@
+//! The `mul` state machines accept each message only in its turn, and a
+//! message out of turn aborts them for good.
+
+use du_attalah::error::ProtocolError;
+use du_attalah::roles::state::{ClientMachine, Message, ServerMachine, State, Values};
+
+fn values(x: u64, y: u64) -> Values {
+    Values { x, y, auth: "ab".into() }
+}
+
+#[test]
+fn server_walks_through_every_state() {
+    let mut machine = ServerMachine::default();
+    assert_eq!(machine.receive(2, Message::Shares(values(5, 6))).unwrap(), values(5, 6));
+    assert_eq!(machine.state(), State::AwaitingPeers);
+    machine.receive(1, Message::Shares(values(3, 4))).unwrap();
+    assert_eq!(machine.state(), State::SharesReceived);
+    machine.masks_sent();
+    assert_eq!(machine.state(), State::MasksSent);
+    machine.receive(1, Message::Relay(values(7, 8))).unwrap();
+    machine.receive(2, Message::Relay(values(9, 10))).unwrap();
+    assert_eq!(machine.state(), State::Exchanged);
+    machine.done();
+    assert_eq!(machine.state(), State::Done);
+}
+
+#[test]
+fn relay_before_the_masks_is_rejected() {
+    let mut machine = ServerMachine::default();
+    machine.receive(1, Message::Shares(values(3, 4))).unwrap();
+    let err = machine.receive(2, Message::Relay(values(7, 8))).unwrap_err();
+    assert_eq!(err.kind(), "malformed");
+    assert!(err.to_string().contains("Client2 sent relay out of order"), "{}", err);
+    assert_eq!(machine.state(), State::Aborted);
+}
+
+#[test]
+fn duplicate_shares_abort_the_server_for_good() {
+    let mut machine = ServerMachine::default();
+    machine.receive(1, Message::Shares(values(3, 4))).unwrap();
+    let err = machine.receive(1, Message::Shares(values(3, 4))).unwrap_err();
+    assert!(err.to_string().contains("Client1 sent shares twice"), "{}", err);
+    assert_eq!(machine.state(), State::Aborted);
+
+    // Even the message that would have been next is refused now
+    let err = machine.receive(2, Message::Shares(values(5, 6))).unwrap_err();
+    assert!(matches!(err, ProtocolError::Malformed(_)));
+    assert_eq!(machine.state(), State::Aborted);
+}
+
+#[test]
+fn client_walks_through_every_state_and_refuses_repeats() {
+    let mut machine = ClientMachine::new("client1");
+    assert!(machine.receive("Client2", Message::Masked(values(1, 2))).is_err());
+
+    let mut machine = ClientMachine::new("client1");
+    machine.receive("Server", Message::Masks(values(1, 2))).unwrap();
+    assert_eq!(machine.state(), State::SharesReceived);
+    machine.committed();
+    assert_eq!(machine.state(), State::Committed);
+    assert_eq!(machine.receive("Client2", Message::Commit("cd".into())).unwrap().auth, "cd");
+    assert_eq!(machine.state(), State::CommitmentsExchanged);
+    machine.masks_sent();
+    machine.receive("Client2", Message::Masked(values(3, 4))).unwrap();
+    assert_eq!(machine.state(), State::Exchanged);
+    machine.done();
+    assert_eq!(machine.state(), State::Done);
+
+    let err = machine.receive("Client2", Message::Masked(values(3, 4))).unwrap_err();
+    assert!(err.to_string().contains("after the session ended"), "{}", err);
+    assert_eq!(machine.state(), State::Aborted);
+}
+
+#[test]
+fn peer_values_are_refused_until_both_commitments_are_in() {
+    // Before committing to our own masked shares
+    let mut machine = ClientMachine::new("client2");
+    machine.receive("Server", Message::Masks(values(1, 2))).unwrap();
+    let err = machine.receive("Client1", Message::Masked(values(3, 4))).unwrap_err();
+    assert!(err.to_string().contains("Client1 sent masked out of order"), "{}", err);
+    assert_eq!(machine.state(), State::Aborted);
+
+    // After committing, but before the peer has
+    let mut machine = ClientMachine::new("client2");
+    machine.receive("Server", Message::Masks(values(1, 2))).unwrap();
+    machine.committed();
+    let err = machine.receive("Client1", Message::Masked(values(3, 4))).unwrap_err();
+    assert!(err.to_string().contains("Client1 sent masked out of order"), "{}", err);
+    assert_eq!(machine.state(), State::Aborted);
+
+    // A commitment before the masked shares it would cover is refused too
+    let mut machine = ClientMachine::new("client2");
+    assert!(machine.receive("Client1", Message::Commit("cd".into())).is_err());
+}
+
+#[test]
+fn messages_round_trip_as_tagged_lines() {
+    let message = Message::Relay(Values { x: 3, y: 1_000_000_006, auth: "00ff".into() });
+    assert_eq!(message.to_string(), "relay 3 1000000006 00ff");
+    assert_eq!("relay 3 1000000006 00ff".parse::<Message>().unwrap(), message);
+    assert!("relay 1 2".parse::<Message>().is_err());
+    assert!("relay -3 1 00ff".parse::<Message>().is_err());
+    assert!("hello 1 2 00".parse::<Message>().is_err());
+    assert_eq!("commit 00ff".parse::<Message>().unwrap(), Message::Commit("00ff".into()));
+    assert_eq!(Message::Commit("00ff".into()).to_string(), "commit 00ff");
+    assert!("commit 1 2 00".parse::<Message>().is_err());
+}
+
+#[test]
+fn values_are_redacted_in_debug() {
+    let message = Message::Masks(Values { x: 424242, y: 434343, auth: "beefcafe".into() });
+    let shown = format!("{:?}", message);
+    assert!(shown.starts_with("Masks(Values"));
+    for leaked in ["424242", "434343", "beefcafe"] {
+        assert!(!shown.contains(leaked), "{} leaked into {}", leaked, shown);
+    }
+}
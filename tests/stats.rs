@@ -0,0 +1,83 @@
+//! `stats` opens the count, mean and variance of the combined dataset, and
+//! refuses a dataset with no records.
+
+mod common;
+
+use proptest::prelude::*;
+
+use uuid::Uuid;
+
+use du_attalah::channel::Channel;
+use du_attalah::error::{self, ProtocolError};
+use du_attalah::operation::Operation;
+use du_attalah::randomness::SeededRandom;
+use du_attalah::rendezvous::{self, Topology};
+use du_attalah::report::Report;
+use du_attalah::roles::server::{self, Outcome};
+use du_attalah::transport::{Link, Transcript};
+
+/// One client's records as the line it would type.
+fn line(values: &[u64]) -> String {
+    let values: Vec<String> = values.iter().map(u64::to_string).collect();
+    format!("{}\n", values.join(" "))
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(32))]
+
+    /// Small records keep every sum of squares below the field modulus, so
+    /// the opened moments match a plain computation exactly.
+    #[test]
+    fn stats_session_matches_a_plain_computation(
+        records in prop::collection::vec((0..1_000_000u64, 0..1_000_000u64), 1..16),
+        seed in any::<u64>(),
+    ) {
+        let (a, b): (Vec<u64>, Vec<u64>) = records.iter().copied().unzip();
+        let Outcome::Statistics(stats) = common::run(Operation::Stats, [&line(&a), &line(&b)], seed).outcome else {
+            panic!("stats session did not open statistics");
+        };
+
+        let values: Vec<f64> = records.iter().map(|&(a, b)| (a + b) as f64).collect();
+        let n = values.len() as f64;
+        let mean = values.iter().sum::<f64>() / n;
+        let variance = values.iter().map(|v| v * v).sum::<f64>() / n - mean * mean;
+        prop_assert_eq!(stats.count, records.len());
+        prop_assert!((stats.mean() - mean).abs() <= 1e-9 * mean.max(1.0), "mean {} != {}", stats.mean(), mean);
+        prop_assert!((stats.variance() - variance).abs() <= 1e-6 * (mean * mean).max(1.0), "variance {} != {}", stats.variance(), variance);
+    }
+}
+
+#[test]
+fn variance_of_large_close_values_does_not_cancel() {
+    for ([a, b], expected) in [(["100000000 100000000 100000001\n", "0 0 0\n"], 2.0 / 9.0), (["1000000000 1000000001\n", "0 0\n"], 0.25)] {
+        let Outcome::Statistics(stats) = common::run(Operation::Stats, [a, b], 1).outcome else {
+            panic!("stats session did not open statistics");
+        };
+        assert!((stats.variance() - expected).abs() < 1e-12, "variance {} != {}", stats.variance(), expected);
+    }
+}
+
+/// A client that claims an empty dataset once paired.
+async fn empty_client(server: &mut Link, k: usize) -> anyhow::Result<()> {
+    let mut server = Channel::new(server, "Server");
+    server.send(&Operation::Stats).await?;
+    let _session: Uuid = server.recv().await?;
+    rendezvous::pair(&mut server, &rendezvous::socket(k)).await?;
+    server.send(&0).await
+}
+
+#[tokio::test]
+async fn server_refuses_an_empty_dataset() {
+    let transcript = Transcript::default();
+    let (server1, mut to_server1) = Link::in_memory("server", "client1", &transcript);
+    let (server2, mut to_server2) = Link::in_memory("server", "client2", &transcript);
+
+    let server = async move {
+        let mut links = [server1, server2];
+        server::run(&mut links, &Topology::Direct, &mut SeededRandom::new(3, 0), &mut Report::new("server")).await
+    };
+    let (outcome, _, _) = tokio::join!(server, empty_client(&mut to_server1, 1), empty_client(&mut to_server2, 2));
+    let err = outcome.unwrap_err();
+    assert!(matches!(error::protocol_error(&err), Some(ProtocolError::Malformed(_))), "{:#}", err);
+    assert!(err.to_string().contains("empty dataset"), "{}", err);
+}